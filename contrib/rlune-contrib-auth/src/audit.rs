@@ -0,0 +1,106 @@
+use rlune_core::audit::AuditEvent;
+use rlune_core::audit::AuditOutcome;
+
+/// Security relevant events recorded by the [`AuthModule`](crate::AuthModule)
+#[derive(Debug, Clone)]
+pub enum AuthAuditEvent {
+    /// An attempt to log in
+    Login {
+        /// The identifier the user tried to log in with
+        ///
+        /// It is empty for oidc logins which failed before the provider revealed it.
+        identifier: String,
+        /// The login method
+        method: LoginMethod,
+        /// Whether the attempt succeeded
+        outcome: AuditOutcome,
+    },
+
+    /// An account logged out
+    Logout {
+        /// The account's primary key
+        account: i64,
+    },
+
+    /// An account set or changed its password
+    PasswordChanged {
+        /// The account's primary key
+        account: i64,
+    },
+
+    /// An account removed its password
+    PasswordRemoved {
+        /// The account's primary key
+        account: i64,
+    },
+
+    /// An account enrolled a new passkey
+    PasskeyEnrolled {
+        /// The account's primary key
+        account: i64,
+        /// The passkey's label
+        label: String,
+    },
+
+    /// An account removed a passkey
+    PasskeyRemoved {
+        /// The account's primary key
+        account: i64,
+        /// The passkey's label
+        label: String,
+    },
+}
+
+/// The methods an account can log in with
+#[derive(Debug, Copy, Clone)]
+pub enum LoginMethod {
+    /// Openid connect
+    Oidc,
+    /// Local password
+    Password,
+    /// Local passkey
+    Webauthn,
+}
+
+impl LoginMethod {
+    fn as_str(self) -> &'static str {
+        match self {
+            LoginMethod::Oidc => "oidc",
+            LoginMethod::Password => "password",
+            LoginMethod::Webauthn => "webauthn",
+        }
+    }
+}
+
+impl From<AuthAuditEvent> for AuditEvent {
+    fn from(value: AuthAuditEvent) -> Self {
+        match value {
+            AuthAuditEvent::Login {
+                identifier,
+                method,
+                outcome,
+            } => AuditEvent::new("auth", "login", outcome)
+                .subject(identifier)
+                .detail("method", method.as_str()),
+            AuthAuditEvent::Logout { account } => {
+                AuditEvent::new("auth", "logout", AuditOutcome::Success).subject(account)
+            }
+            AuthAuditEvent::PasswordChanged { account } => {
+                AuditEvent::new("auth", "password_changed", AuditOutcome::Success).subject(account)
+            }
+            AuthAuditEvent::PasswordRemoved { account } => {
+                AuditEvent::new("auth", "password_removed", AuditOutcome::Success).subject(account)
+            }
+            AuthAuditEvent::PasskeyEnrolled { account, label } => {
+                AuditEvent::new("auth", "passkey_enrolled", AuditOutcome::Success)
+                    .subject(account)
+                    .detail("label", label)
+            }
+            AuthAuditEvent::PasskeyRemoved { account, label } => {
+                AuditEvent::new("auth", "passkey_removed", AuditOutcome::Success)
+                    .subject(account)
+                    .detail("label", label)
+            }
+        }
+    }
+}
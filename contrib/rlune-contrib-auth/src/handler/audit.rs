@@ -0,0 +1,47 @@
+use rlune_core::audit::AuditEvent;
+use rlune_core::audit::DatabaseAuditSink;
+use rlune_core::re_exports::axum::extract::Query;
+use rlune_core::re_exports::axum::Json;
+use rlune_core::session::Session;
+use rlune_core::stuff::api_error::ApiError;
+use rlune_core::stuff::api_error::ApiResult;
+use rlune_core::stuff::schema::ApiStatusCode;
+use rlune_core::stuff::schema::GetPageRequest;
+use rlune_core::stuff::schema::Page;
+use rlune_core::Module;
+use rlune_macros::get;
+
+use crate::AuthModule;
+
+/// The maximum number of events returned by a single request
+const MAX_LIMIT: u64 = 1000;
+
+/// Queries a page of recorded audit events, newest first
+///
+/// This is restricted to admins (see `AuthSetup::is_admin`)
+/// and only returns events recorded by the `DatabaseAuditSink`.
+#[get("/audit", core_crate = "::rlune_core")]
+pub async fn get_audit_events(
+    session: Session,
+    Query(request): Query<GetPageRequest>,
+) -> ApiResult<Json<Page<AuditEvent>>> {
+    let account_pk: i64 = session
+        .get("account")
+        .await?
+        .ok_or(ApiError::bad_request("Not logged-in"))?;
+
+    let module = AuthModule::global();
+    if !(module.is_admin)(account_pk).await {
+        return Err(ApiError::new(
+            ApiStatusCode::MissingPrivileges,
+            "Only admins may query the audit log",
+        ));
+    }
+
+    if request.limit > MAX_LIMIT {
+        return Err(ApiError::bad_request("Limit is too large"));
+    }
+
+    let page = DatabaseAuditSink::query(&module.db, request).await?;
+    Ok(Json(page))
+}
@@ -6,6 +6,7 @@ use rlune_core::Module;
 use rlune_macros::delete;
 use rlune_macros::put;
 
+use crate::audit::AuthAuditEvent;
 use crate::models::LocalAccount;
 use crate::models::WebAuthnKey;
 use crate::AuthModule;
@@ -40,6 +41,13 @@ pub async fn set_local_password(
 
     tx.commit().await?;
 
+    AuthModule::global()
+        .audit
+        .record(AuthAuditEvent::PasswordChanged {
+            account: account_pk,
+        })
+        .await;
+
     Ok(())
 }
 
@@ -74,5 +82,13 @@ pub async fn delete_local_password(session: Session) -> ApiResult<()> {
         .await?;
 
     tx.commit().await?;
+
+    AuthModule::global()
+        .audit
+        .record(AuthAuditEvent::PasswordRemoved {
+            account: account_pk,
+        })
+        .await;
+
     Ok(())
 }
@@ -1,3 +1,4 @@
+use rlune_core::audit::AuditOutcome;
 use rlune_core::re_exports::axum::extract::Query;
 use rlune_core::re_exports::axum::Json;
 use rlune_core::session::Session;
@@ -10,6 +11,8 @@ use serde::Deserialize;
 use serde::Serialize;
 use webauthn_rs::prelude::AttestedPasskeyAuthentication;
 
+use crate::audit::AuthAuditEvent;
+use crate::audit::LoginMethod;
 use crate::handler::schema::GetLoginFlowsRequest;
 use crate::handler::schema::GetLoginFlowsResponse;
 use crate::handler::schema::LocalLoginFlow;
@@ -30,6 +33,8 @@ mod oidc;
 #[cfg(feature = "oidc")]
 pub use self::oidc::*;
 
+mod audit;
+pub use self::audit::*;
 mod local;
 pub use self::local::*;
 mod schema;
@@ -149,9 +154,33 @@ pub async fn finish_login_local_webauthn(
         .await?
         .ok_or(ApiError::bad_request("No ongoing challenge"))?;
 
+    let result = check_local_webauthn(&identifier, &request, &state).await;
+    AuthModule::global()
+        .audit
+        .record(AuthAuditEvent::Login {
+            identifier,
+            method: LoginMethod::Webauthn,
+            outcome: AuditOutcome::of(&result),
+        })
+        .await;
+    let account_pk = result?;
+
+    // Prevent session fixation by issuing a new id upon login
+    session.cycle_id().await?;
+    session.insert("account", account_pk).await?;
+
+    Ok(())
+}
+
+/// Checks a finished webauthn challenge returning the account's primary key
+async fn check_local_webauthn(
+    identifier: &str,
+    request: &PublicKeyCredential,
+    state: &AttestedPasskeyAuthentication,
+) -> ApiResult<i64> {
     let authentication_result = AuthModule::global()
         .webauthn
-        .finish_attested_passkey_authentication(&request.0, &state)
+        .finish_attested_passkey_authentication(&request.0, state)
         .map_err(ApiError::map_server_error(
             "Failed to finish webauthn challenge",
         ))?;
@@ -159,7 +188,7 @@ pub async fn finish_login_local_webauthn(
     let mut tx = AuthModule::global().db.start_transaction().await?;
 
     let account_pk = rorm::query(&mut tx, Account.pk)
-        .condition(Account.id.equals(&identifier))
+        .condition(Account.id.equals(identifier))
         .optional()
         .await?
         .ok_or(ApiError::bad_request("Account not found"))?;
@@ -186,9 +215,7 @@ pub async fn finish_login_local_webauthn(
 
     tx.commit().await?;
 
-    session.insert("account", account_pk).await?;
-
-    Ok(())
+    Ok(account_pk)
 }
 
 #[post("/login/local/password", core_crate = "::rlune_core")]
@@ -196,6 +223,26 @@ pub async fn login_local_password(
     session: Session,
     Json(request): Json<LoginLocalPasswordRequest>,
 ) -> ApiResult<()> {
+    let result = check_local_password(&request).await;
+    AuthModule::global()
+        .audit
+        .record(AuthAuditEvent::Login {
+            identifier: request.identifier,
+            method: LoginMethod::Password,
+            outcome: AuditOutcome::of(&result),
+        })
+        .await;
+    let account_pk = result?;
+
+    // Prevent session fixation by issuing a new id upon login
+    session.cycle_id().await?;
+    session.insert("account", account_pk).await?;
+
+    Ok(())
+}
+
+/// Checks a [`LoginLocalPasswordRequest`] returning the account's primary key
+async fn check_local_password(request: &LoginLocalPasswordRequest) -> ApiResult<i64> {
     let mut tx = AuthModule::global().db.start_transaction().await?;
 
     let account_pk = rorm::query(&mut tx, Account.pk)
@@ -221,13 +268,17 @@ pub async fn login_local_password(
 
     tx.commit().await?;
 
-    session.insert("account", account_pk).await?;
-
-    Ok(())
+    Ok(account_pk)
 }
 
 #[post("/logout", core_crate = "::rlune_core")]
 pub async fn logout(session: Session) -> ApiResult<()> {
-    session.remove::<serde::de::IgnoredAny>("account").await?;
+    if let Some(account) = session.remove::<i64>("account").await? {
+        AuthModule::global()
+            .audit
+            .record(AuthAuditEvent::Logout { account })
+            .await;
+    }
+    session.cycle_id().await?;
     Ok(())
 }
@@ -1,5 +1,4 @@
 use openidconnect::core::CoreAuthenticationFlow;
-use openidconnect::reqwest::async_http_client;
 use openidconnect::AccessTokenHash;
 use openidconnect::CsrfToken;
 use openidconnect::Nonce;
@@ -8,6 +7,7 @@ use openidconnect::PkceCodeChallenge;
 use openidconnect::PkceCodeVerifier;
 use openidconnect::Scope;
 use openidconnect::TokenResponse;
+use rlune_core::audit::AuditOutcome;
 use rlune_core::re_exports::axum::extract::Query;
 use rlune_core::re_exports::axum::response::Redirect;
 use rlune_core::session::Session;
@@ -21,7 +21,10 @@ use rorm::FieldAccess;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::audit::AuthAuditEvent;
+use crate::audit::LoginMethod;
 use crate::handler::schema::FinishLoginOidcRequest;
+use crate::utils::async_http_client;
 use crate::AuthModels;
 use crate::AuthModule;
 
@@ -67,15 +70,45 @@ pub async fn finish_login_oidc<M: AuthModels>(
     session: Session,
     Query(request): Query<FinishLoginOidcRequest>,
 ) -> ApiResult<Redirect> {
-    let LoginOidcSessionData {
-        csrf_token,
-        pkce_code_verifier,
-        nonce,
-    } = session
+    let session_data = session
         .remove("oidc_login_data")
         .await?
         .ok_or("Bad Request")?;
 
+    // The identifier is unknown until the id token has been verified
+    let (identifier, result) = match verify_oidc_login::<M>(request, session_data).await {
+        Ok(oidc_id) => {
+            let result = get_or_create_oidc_account::<M>(&oidc_id).await;
+            (oidc_id, result)
+        }
+        Err(error) => (String::new(), Err(error)),
+    };
+    AuthModule::<M>::global()
+        .audit
+        .record(AuthAuditEvent::Login {
+            identifier,
+            method: LoginMethod::Oidc,
+            outcome: AuditOutcome::of(&result),
+        })
+        .await;
+    let account_pk = result?;
+
+    // Prevent session fixation by issuing a new id upon login
+    session.cycle_id().await?;
+    session.insert("account", account_pk).await?;
+
+    Ok(Redirect::temporary("/"))
+}
+
+/// Verifies the provider's response returning the account's oidc id
+async fn verify_oidc_login<M: AuthModels>(
+    request: FinishLoginOidcRequest,
+    LoginOidcSessionData {
+        csrf_token,
+        pkce_code_verifier,
+        nonce,
+    }: LoginOidcSessionData,
+) -> ApiResult<String> {
     if request.state.secret() != csrf_token.secret() {
         return Err("Bad Request".into());
     }
@@ -105,11 +138,16 @@ pub async fn finish_login_oidc<M: AuthModels>(
         return Err("Missing claim: preferred_username".into());
     };
 
+    Ok(oidc_id)
+}
+
+/// Gets the primary key of the account with the oidc id, creating it if necessary
+async fn get_or_create_oidc_account<M: AuthModels>(oidc_id: &str) -> ApiResult<i64> {
     let mut tx = AuthModule::<M>::global().db.start_transaction().await?;
 
     let account_pk = if let Some((account_fm,)) =
         QueryBuilder::new(&mut tx, (M::oidc_account_fm(),))
-            .condition(M::oidc_account_id().equals(&oidc_id))
+            .condition(M::oidc_account_id().equals(oidc_id))
             .optional()
             .await?
     {
@@ -124,12 +162,15 @@ pub async fn finish_login_oidc<M: AuthModels>(
 
         let account_pk = insert!(&mut tx, M::Account)
             .return_primary_key()
-            .single(&M::insertable_account(oidc_id.clone()))
+            .single(&M::insertable_account(oidc_id.to_string()))
             .await?;
 
         insert!(&mut tx, M::OidcAccount)
             .return_nothing()
-            .single(&M::insertable_oidc_account(oidc_id, &account_pk))
+            .single(&M::insertable_oidc_account(
+                oidc_id.to_string(),
+                &account_pk,
+            ))
             .await?;
 
         account_pk
@@ -137,7 +178,5 @@ pub async fn finish_login_oidc<M: AuthModels>(
 
     tx.commit().await?;
 
-    session.insert("account", account_pk).await?;
-
-    Ok(Redirect::temporary("/"))
+    Ok(account_pk)
 }
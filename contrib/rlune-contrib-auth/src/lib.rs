@@ -1,7 +1,12 @@
+pub mod audit;
 pub mod handler;
 mod models;
 mod module;
+#[cfg(feature = "oidc")]
+mod utils;
 
 pub use models::Account;
 pub use models::MaybeAttestedPasskey;
 pub use module::AuthModule;
+pub use module::AuthSetup;
+pub use module::IsAdmin;
@@ -3,16 +3,19 @@ use std::future::ready;
 use std::future::Future;
 use std::io;
 use std::path::PathBuf;
+use std::pin::Pin;
 
 #[cfg(feature = "oidc")]
 use openidconnect::core::CoreClient as OidcClient;
 #[cfg(feature = "oidc")]
 use openidconnect::core::CoreProviderMetadata;
-#[cfg(feature = "oidc")]
-use openidconnect::reqwest::async_http_client;
 use openidconnect::ClientId;
 use openidconnect::ClientSecret;
 use openidconnect::IssuerUrl;
+use rlune_core::audit::AuditLog;
+use rlune_core::audit::AuditSink;
+use rlune_core::audit::DatabaseAuditSink;
+use rlune_core::DependencyRefs;
 use rlune_core::InitError;
 use rlune_core::Module;
 use rlune_core::PreInitError;
@@ -26,6 +29,8 @@ use webauthn_rs::Webauthn;
 use webauthn_rs::WebauthnBuilder;
 
 use crate::handler;
+#[cfg(feature = "oidc")]
+use crate::utils::async_http_client;
 
 #[cfg(not(feature = "oidc"))]
 type OidcClient = ();
@@ -38,13 +43,54 @@ pub struct AuthModule {
     pub(crate) oidc: OidcClient,
     pub(crate) webauthn: Webauthn,
     pub(crate) attestation_ca_list: AttestationCaList,
+    pub audit: AuditLog,
+    pub(crate) is_admin: IsAdmin,
 }
 
-#[derive(Debug, Default)]
+/// Decides whether the account identified by its primary key is an admin
+///
+/// Admins may access endpoints like the audit log.
+pub type IsAdmin = fn(i64) -> Pin<Box<dyn Future<Output = bool> + Send>>;
+
+#[derive(Debug)]
 pub struct AuthSetup {
+    /// Where to record the module's [`AuthAuditEvent`](crate::audit::AuthAuditEvent)s
+    ///
+    /// Defaults to the [`DatabaseAuditSink`].
+    audit_sink: Box<dyn AuditSink>,
+
+    /// Decides which accounts may access the admin endpoints
+    ///
+    /// Defaults to denying every account.
+    is_admin: IsAdmin,
+
     private: (),
 }
 
+impl Default for AuthSetup {
+    fn default() -> Self {
+        Self {
+            audit_sink: Box::new(DatabaseAuditSink),
+            is_admin: |_| Box::pin(ready(false)),
+            private: (),
+        }
+    }
+}
+
+impl AuthSetup {
+    /// Sets the sink the module's audit events are recorded to
+    pub fn audit_sink(mut self, sink: impl AuditSink) -> Self {
+        self.audit_sink = Box::new(sink);
+        self
+    }
+
+    /// Sets the function deciding which accounts may access the admin endpoints
+    pub fn is_admin(mut self, is_admin: IsAdmin) -> Self {
+        self.is_admin = is_admin;
+        self
+    }
+}
+
 #[non_exhaustive]
 pub struct AuthHandler {
     pub get_login_flow: handler::get_login_flow,
@@ -66,6 +112,8 @@ pub struct AuthHandler {
     pub login_local_password: handler::login_local_password,
     pub set_local_password: handler::set_local_password,
     pub delete_local_password: handler::delete_local_password,
+
+    pub get_audit_events: handler::get_audit_events,
 }
 
 impl Clone for AuthHandler {
@@ -104,15 +152,26 @@ impl AuthHandler {
 
         router
     }
+
+    /// Constructs a router containing the audit log's query endpoint
+    ///
+    /// The handler is restricted to accounts accepted by [`AuthSetup::is_admin`].
+    pub fn as_audit_router(&self) -> RluneRouter {
+        RluneRouter::new().handler(self.get_audit_events)
+    }
 }
 
 impl Module for AuthModule {
     type Setup = AuthSetup;
 
-    type PreInit = (OidcClient, Webauthn, AttestationCaList);
+    type PreInit = (OidcClient, Webauthn, AttestationCaList, AuditLog, IsAdmin);
 
     async fn pre_init(
-        AuthSetup { private: () }: Self::Setup,
+        AuthSetup {
+            audit_sink,
+            is_admin,
+            private: (),
+        }: Self::Setup,
     ) -> Result<Self::PreInit, PreInitError> {
         let auth_config: AuthConfig = envy::from_env()?;
 
@@ -134,20 +193,28 @@ impl Module for AuthModule {
             &auth_config.webauthn_attestation_ca_list,
         )?))?;
 
-        Ok((oidc, webauthn, attestation_ca_list))
+        Ok((
+            oidc,
+            webauthn,
+            attestation_ca_list,
+            AuditLog::new(audit_sink),
+            is_admin,
+        ))
     }
 
     type Dependencies = (Database,);
 
     fn init(
-        (oidc, webauthn, attestation_ca_list): Self::PreInit,
-        (db,): &mut Self::Dependencies,
+        (oidc, webauthn, attestation_ca_list, audit, is_admin): Self::PreInit,
+        (db,): DependencyRefs<Self>,
     ) -> impl Future<Output = Result<Self, InitError>> + Send {
         ready(Ok(Self {
             db: db.clone(),
             oidc,
             webauthn,
             attestation_ca_list,
+            audit,
+            is_admin,
             handler: AuthHandler {
                 get_login_flow: Default::default(),
                 logout: Default::default(),
@@ -160,6 +227,8 @@ impl Module for AuthModule {
                 login_local_password: Default::default(),
                 set_local_password: Default::default(),
                 delete_local_password: Default::default(),
+
+                get_audit_events: Default::default(),
             },
         }))
     }
@@ -1,29 +1,23 @@
-use openidconnect::reqwest::Error as OidcError;
+use openidconnect::http::HeaderName;
+use openidconnect::http::HeaderValue;
+use openidconnect::reqwest::AsyncHttpClientError;
+use openidconnect::HttpRequest;
+use openidconnect::HttpResponse;
+use rlune_core::stuff::trace_context;
 
 /// Function used to send the actual openid connect requests
+///
+/// It propagates the current trace context to the identity provider.
 pub async fn async_http_client(
-    request: oauth2::HttpRequest,
-) -> Result<oauth2::HttpResponse, OidcError<reqwest::Error>> {
-    let client = reqwest::Client::builder()
-        .danger_accept_invalid_certs(true) // TODO
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .map_err(OidcError::Reqwest)?;
-
-    let mut request_builder = client
-        .request(request.method, request.url.as_str())
-        .body(request.body);
-    for (name, value) in &request.headers {
-        request_builder = request_builder.header(name.as_str(), value.as_bytes());
-    }
-    let response = request_builder.send().await.map_err(OidcError::Reqwest)?;
-
-    let status_code = response.status();
-    let headers = response.headers().to_owned();
-    let chunks = response.bytes().await.map_err(OidcError::Reqwest)?;
-    Ok(oauth2::HttpResponse {
-        status_code,
-        headers,
-        body: chunks.to_vec(),
-    })
-}
\ No newline at end of file
+    mut request: HttpRequest,
+) -> Result<HttpResponse, AsyncHttpClientError> {
+    trace_context::inject(|name, value| {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            request.headers.insert(name, value);
+        }
+    });
+    openidconnect::reqwest::async_http_client(request).await
+}
@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::future::Future;
+
+use futures_lite::future;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use time::OffsetDateTime;
+
+/// Error returned by a failing [`Job`]
+pub type JobError = Box<dyn Error + Send + Sync + 'static>;
+
+/// A type of background job
+///
+/// The job's data is its payload which is serialized as json into the database.
+pub trait Job: Serialize + DeserializeOwned + Send + 'static {
+    /// Unique name identifying this type of job in the database
+    ///
+    /// Changing it will orphan jobs which are already enqueued.
+    const KIND: &'static str;
+
+    /// The number of times the job is tried before it is moved to the dead letters
+    const MAX_ATTEMPTS: u32 = 5;
+
+    /// Executes the job
+    ///
+    /// A job might be executed more than once
+    /// (for example if the application crashes while it is running),
+    /// so it should be idempotent.
+    fn run(self) -> impl Future<Output = Result<(), JobError>> + Send;
+}
+
+/// Options for [`JobQueue::enqueue`](crate::JobQueue::enqueue)
+#[derive(Debug, Clone, Default)]
+pub struct EnqueueOptions {
+    /// The earliest point in time the job should run at
+    ///
+    /// Defaults to "now".
+    pub run_at: Option<OffsetDateTime>,
+
+    /// A key identifying the job
+    ///
+    /// While a job with the same key is waiting or running,
+    /// enqueueing another one is a no-op.
+    pub unique_key: Option<String>,
+}
+
+/// Type erased function deserializing a job's payload and running it
+pub(crate) type JobRunner = fn(&[u8]) -> future::Boxed<Result<(), JobError>>;
+
+/// The [`JobRunner`] for `J`
+pub(crate) fn run_job<J: Job>(payload: &[u8]) -> future::Boxed<Result<(), JobError>> {
+    match serde_json::from_slice::<J>(payload) {
+        Ok(job) => Box::pin(job.run()),
+        Err(error) => Box::pin(async move { Err(error.into()) }),
+    }
+}
+
+/// Maximum length of error messages stored in the database
+const MAX_ERROR_LENGTH: usize = 1024;
+
+/// Formats an error to be stored in the database, truncating it if necessary
+pub(crate) fn error_message(error: &JobError) -> String {
+    let mut message = error.to_string();
+    if message.len() > MAX_ERROR_LENGTH {
+        let mut end = MAX_ERROR_LENGTH;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+    }
+    message
+}
@@ -0,0 +1,47 @@
+//! Durable background jobs stored in the database
+//!
+//! Jobs are typed using the [`Job`] trait, enqueued using the [`JobQueue`] module
+//! and processed by a configurable number of workers (see [`JobQueueSetup`]).
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct SendMail {
+//!     to: String,
+//! }
+//!
+//! impl Job for SendMail {
+//!     const KIND: &'static str = "send-mail";
+//!
+//!     async fn run(self) -> Result<(), JobError> {
+//!         Mailer::global().send(&self.to).await?;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut setup = JobQueueSetup::default();
+//! setup.register::<SendMail>();
+//! rlune.register_module::<JobQueue>(setup);
+//!
+//! // Later, for example in a handler
+//! JobQueue::global()
+//!     .enqueue(&SendMail { to }, EnqueueOptions::default())
+//!     .await?;
+//! ```
+//!
+//! Jobs are claimed using `SELECT ... FOR UPDATE SKIP LOCKED`,
+//! so this module requires postgres.
+//!
+//! Recurring work can be scheduled using cron expressions with the [`Scheduler`](scheduler::Scheduler) module.
+
+pub mod job;
+pub mod models;
+mod module;
+pub mod scheduler;
+pub mod setup;
+
+pub use self::job::EnqueueOptions;
+pub use self::job::Job;
+pub use self::job::JobError;
+pub use self::module::JobQueue;
+pub use self::module::JobQueueError;
+pub use self::setup::JobQueueSetup;
@@ -0,0 +1,128 @@
+use rlune_core::re_exports::uuid::Uuid;
+use rorm::DbEnum;
+use rorm::Model;
+use rorm::fields::types::Json;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use time::OffsetDateTime;
+
+/// A job stored in the queue
+///
+/// Successful jobs are deleted.
+#[derive(Model)]
+pub struct RluneJob {
+    /// The job's unique identifier
+    #[rorm(primary_key)]
+    pub uuid: Uuid,
+
+    /// The [`Job::KIND`](crate::Job::KIND) of the job
+    #[rorm(max_length = 255)]
+    pub kind: String,
+
+    /// The job's serialized data
+    pub payload: Json<Value>,
+
+    /// The job's state
+    pub status: JobStatus,
+
+    /// The number of times the job has been started
+    pub attempts: i32,
+
+    /// The number of attempts after which the job is moved to the dead letters
+    pub max_attempts: i32,
+
+    /// The earliest point in time the job may run at
+    pub run_at: OffsetDateTime,
+
+    /// The point in time a worker claimed the job or last renewed its claim
+    pub locked_at: Option<OffsetDateTime>,
+
+    /// Token identifying the claim of the worker executing the job
+    ///
+    /// A worker only stores the job's outcome if its claim hasn't been taken over.
+    pub claim: Option<Uuid>,
+
+    /// Key preventing the same job from being enqueued twice
+    ///
+    /// It is cleared once a job becomes dead.
+    #[rorm(unique, max_length = 255)]
+    pub unique_key: Option<String>,
+
+    /// The error of the last failed attempt
+    #[rorm(max_length = 1024)]
+    pub last_error: Option<String>,
+
+    /// The point in time the job has been enqueued
+    pub created_at: OffsetDateTime,
+}
+
+/// The state of a [`RluneJob`]
+#[derive(DbEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    /// The job waits for its `run_at`
+    Pending,
+
+    /// A worker is executing the job
+    Running,
+
+    /// The job failed too often and won't be retried automatically
+    Dead,
+}
+
+/// The state of a schedule of the [`Scheduler`](crate::scheduler::Scheduler) shared by all replicas
+#[derive(Model)]
+pub struct RluneSchedule {
+    /// The schedule's name
+    #[rorm(primary_key, max_length = 255)]
+    pub name: String,
+
+    /// The cron expression `next_run_at` has been calculated with
+    #[rorm(max_length = 255)]
+    pub cron: String,
+
+    /// The timezone `next_run_at` has been calculated in
+    #[rorm(max_length = 255)]
+    pub timezone: String,
+
+    /// The next tick
+    ///
+    /// The replica which advances it gets to run the tick.
+    pub next_run_at: OffsetDateTime,
+}
+
+/// A single run of a schedule
+#[derive(Model)]
+pub struct RluneScheduleRun {
+    /// The run's unique identifier
+    #[rorm(primary_key)]
+    pub uuid: Uuid,
+
+    /// The name of the [`RluneSchedule`]
+    #[rorm(max_length = 255, index)]
+    pub schedule: String,
+
+    /// The point in time the run started
+    pub started_at: OffsetDateTime,
+
+    /// The run's duration in milliseconds
+    pub duration_ms: i64,
+
+    /// Whether the run succeeded
+    pub outcome: RunOutcome,
+
+    /// The error of a failed run
+    #[rorm(max_length = 1024)]
+    pub error: Option<String>,
+}
+
+/// Whether a [`RluneScheduleRun`] succeeded
+#[derive(DbEnum, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum RunOutcome {
+    /// The run succeeded
+    Success,
+
+    /// The run returned an error or panicked
+    Failure,
+}
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::mem;
+use std::panic::AssertUnwindSafe;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use futures_lite::FutureExt;
+use futures_lite::future;
+use rlune_core::DependencyRefs;
+use rlune_core::InitError;
+use rlune_core::Module;
+use rlune_core::PostInitError;
+use rlune_core::PreInitError;
+use rlune_core::ShutdownError;
+use rlune_core::re_exports::uuid::Uuid;
+use rlune_core::registry::Registry;
+use rlune_core::registry::tasks::Cancellation;
+use rlune_core::registry::tasks::RestartPolicy;
+use rlune_core::registry::tasks::TaskError;
+use rorm::Database;
+use rorm::Model;
+use rorm::db::Executor;
+use rorm::db::executor::Nothing;
+use rorm::db::executor::Optional;
+use rorm::db::sql::value::NullType;
+use rorm::db::sql::value::Value as SqlValue;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::Instrument;
+use tracing::debug;
+use tracing::error;
+use tracing::info_span;
+use tracing::warn;
+
+use crate::job::EnqueueOptions;
+use crate::job::Job;
+use crate::job::JobError;
+use crate::job::JobRunner;
+use crate::job::error_message;
+use crate::models::JobStatus;
+use crate::models::RluneJob;
+use crate::setup::JobQueueSetup;
+
+/// Module storing background jobs in the database and processing them
+///
+/// (see [crate level docs](crate))
+pub struct JobQueue {
+    db: Database,
+
+    workers: usize,
+    poll_interval: Duration,
+    retry_base_delay: Duration,
+    retry_max_delay: Duration,
+    lease: Duration,
+    jobs: HashMap<&'static str, JobRunner>,
+
+    /// The jobs currently executed by this process's workers mapped to their claims
+    in_flight: Mutex<HashMap<Uuid, Uuid>>,
+}
+
+/// A job claimed by a worker
+struct ClaimedJob {
+    uuid: Uuid,
+    claim: Uuid,
+    kind: String,
+    payload: Vec<u8>,
+    attempts: i32,
+    max_attempts: i32,
+}
+
+impl JobQueue {
+    /// Enqueues a job
+    ///
+    /// Returns the job's uuid or `None` if a job with the same [`EnqueueOptions::unique_key`] is already enqueued.
+    pub async fn enqueue<J: Job>(
+        &self,
+        job: &J,
+        options: EnqueueOptions,
+    ) -> Result<Option<Uuid>, JobQueueError> {
+        self.enqueue_in(&self.db, job, options).await
+    }
+
+    /// Enqueues a job using an arbitrary executor
+    ///
+    /// Passing a transaction ensures the job is only enqueued if the transaction is committed.
+    pub async fn enqueue_in<'e, J: Job>(
+        &self,
+        executor: impl Executor<'e>,
+        job: &J,
+        options: EnqueueOptions,
+    ) -> Result<Option<Uuid>, JobQueueError> {
+        let uuid = Uuid::new_v4();
+        let payload = serde_json::to_vec(job)?;
+        let now = OffsetDateTime::now_utc();
+
+        // rorm doesn't support `ON CONFLICT`, so it is written by hand.
+        let inserted = executor
+            .execute::<Optional>(
+                format!(
+                    r#"INSERT INTO "{table}" ("uuid", "kind", "payload", "status", "attempts", "max_attempts", "run_at", "locked_at", "claim", "unique_key", "last_error", "created_at") VALUES ($1, $2, $3, 'Pending', 0, $4, $5, NULL, NULL, $6, NULL, $7) ON CONFLICT ("unique_key") DO NOTHING RETURNING "uuid";"#,
+                    table = RluneJob::TABLE,
+                ),
+                vec![
+                    SqlValue::Uuid(uuid),
+                    SqlValue::String(J::KIND),
+                    SqlValue::Binary(&payload),
+                    SqlValue::I32(i32::try_from(J::MAX_ATTEMPTS).unwrap_or(i32::MAX)),
+                    SqlValue::TimeOffsetDateTime(options.run_at.unwrap_or(now)),
+                    match &options.unique_key {
+                        Some(unique_key) => SqlValue::String(unique_key),
+                        None => SqlValue::Null(NullType::String),
+                    },
+                    SqlValue::TimeOffsetDateTime(now),
+                ],
+            )
+            .await?;
+
+        if inserted.is_some() {
+            debug!(job.uuid = %uuid, job.kind = J::KIND, "Enqueued job");
+            Ok(Some(uuid))
+        } else {
+            debug!(job.kind = J::KIND, "Skipped enqueueing duplicate job");
+            Ok(None)
+        }
+    }
+
+    /// Moves a dead job back into the queue to be run immediately
+    ///
+    /// Returns whether the job has been found.
+    pub async fn retry_dead(&self, uuid: Uuid) -> Result<bool, JobQueueError> {
+        let updated = rorm::update(&self.db, RluneJob)
+            .set(RluneJob.status, JobStatus::Pending)
+            .set(RluneJob.attempts, 0)
+            .set(RluneJob.run_at, OffsetDateTime::now_utc())
+            .condition(rorm::and![
+                RluneJob.uuid.equals(uuid),
+                RluneJob.status.equals(JobStatus::Dead),
+            ])
+            .await?;
+        Ok(updated > 0)
+    }
+
+    /// The loop run by every worker
+    async fn work(&'static self, cancellation: Cancellation) -> Result<(), TaskError> {
+        while !cancellation.is_cancelled() {
+            let job = self.claim().await.unwrap_or_else(|error| {
+                warn!(
+                    error.display = %error,
+                    error.debug = ?error,
+                    "Failed to claim job"
+                );
+                None
+            });
+
+            match job {
+                Some(job) => {
+                    let span = info_span!("job", job.uuid = %job.uuid, job.kind = %job.kind);
+                    self.run(job).instrument(span).await;
+                }
+                None => {
+                    future::or(
+                        cancellation.cancelled(),
+                        tokio::time::sleep(self.poll_interval),
+                    )
+                    .await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Claims the next job which is due or has been abandoned
+    ///
+    /// Only jobs whose kind has been registered in this process are claimed,
+    /// so replicas running an older version leave new kinds to the ones knowing them.
+    async fn claim(&self) -> Result<Option<ClaimedJob>, rorm::Error> {
+        let now = OffsetDateTime::now_utc();
+        let claim = Uuid::new_v4();
+
+        // rorm can't bind arrays, so each kind gets its own parameter.
+        let kinds = (0..self.jobs.len())
+            .map(|index| format!("${}", index + 4))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut values = vec![
+            SqlValue::TimeOffsetDateTime(now),
+            SqlValue::TimeOffsetDateTime(now - self.lease),
+            SqlValue::Uuid(claim),
+        ];
+        values.extend(self.jobs.keys().map(|kind| SqlValue::String(kind)));
+
+        let row = (&self.db)
+            .execute::<Optional>(
+                format!(
+                    r#"UPDATE "{table}" SET "status" = 'Running', "locked_at" = $1, "claim" = $3, "attempts" = "attempts" + 1 WHERE "uuid" = (SELECT "uuid" FROM "{table}" WHERE (("status" = 'Pending' AND "run_at" <= $1) OR ("status" = 'Running' AND "locked_at" <= $2)) AND "kind" IN ({kinds}) ORDER BY "run_at" LIMIT 1 FOR UPDATE SKIP LOCKED) RETURNING "uuid", "kind", "payload", "attempts", "max_attempts";"#,
+                    table = RluneJob::TABLE,
+                ),
+                values,
+            )
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(ClaimedJob {
+            uuid: row.get("uuid")?,
+            claim,
+            kind: row.get("kind")?,
+            payload: row.get("payload")?,
+            attempts: row.get("attempts")?,
+            max_attempts: row.get("max_attempts")?,
+        }))
+    }
+
+    /// Runs a claimed job and stores its outcome
+    async fn run(&self, job: ClaimedJob) {
+        let ClaimedJob {
+            uuid,
+            claim,
+            kind,
+            payload,
+            attempts,
+            max_attempts,
+        } = job;
+
+        self.in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(uuid, claim);
+
+        let result = match self.jobs.get(kind.as_str()) {
+            Some(runner) => {
+                let job = async {
+                    AssertUnwindSafe(runner(&payload))
+                        .catch_unwind()
+                        .await
+                        .unwrap_or_else(|_| Err("The job panicked".into()))
+                };
+                future::or(job, self.heartbeat(uuid, claim)).await
+            }
+            None => Err(format!("No job of kind '{kind}' has been registered").into()),
+        };
+
+        if let Err(error) = self
+            .finish(uuid, claim, attempts, max_attempts, result)
+            .await
+        {
+            error!(
+                error.display = %error,
+                error.debug = ?error,
+                "Failed to store the job's outcome"
+            );
+        }
+
+        self.in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&uuid);
+    }
+
+    /// Renews the claim of a running job until the returned future is dropped
+    ///
+    /// It stops renewing if another worker has taken over the job.
+    async fn heartbeat<T>(&self, uuid: Uuid, claim: Uuid) -> T {
+        loop {
+            tokio::time::sleep(self.lease / 3).await;
+            let renewed = rorm::update(&self.db, RluneJob)
+                .set(RluneJob.locked_at, Some(OffsetDateTime::now_utc()))
+                .condition(rorm::and![
+                    RluneJob.uuid.equals(uuid),
+                    RluneJob.claim.equals(Some(claim)),
+                ])
+                .await;
+            match renewed {
+                Ok(0) => {
+                    warn!("The job has been claimed by another worker while running");
+                    return future::pending().await;
+                }
+                Ok(_) => {}
+                Err(error) => warn!(
+                    error.display = %error,
+                    error.debug = ?error,
+                    "Failed to renew the job's claim"
+                ),
+            }
+        }
+    }
+
+    /// Deletes a successful job or schedules its retry
+    ///
+    /// Nothing is stored if another worker has taken over the job in the meantime.
+    async fn finish(
+        &self,
+        uuid: Uuid,
+        claim: Uuid,
+        attempts: i32,
+        max_attempts: i32,
+        result: Result<(), JobError>,
+    ) -> Result<(), rorm::Error> {
+        let claimed = rorm::and![
+            RluneJob.uuid.equals(uuid),
+            RluneJob.claim.equals(Some(claim)),
+        ];
+        let error = match result {
+            Ok(()) => {
+                debug!("Job succeeded");
+                let deleted = rorm::delete(&self.db, RluneJob).condition(claimed).await?;
+                if deleted == 0 {
+                    warn!("The job has been claimed by another worker while running");
+                }
+                return Ok(());
+            }
+            Err(error) => error,
+        };
+
+        let message = error_message(&error);
+
+        let updated = if attempts >= max_attempts {
+            error!(
+                error.display = %error,
+                error.debug = ?error,
+                job.attempts = attempts,
+                "Job failed for the last time"
+            );
+            rorm::update(&self.db, RluneJob)
+                .set(RluneJob.status, JobStatus::Dead)
+                .set(RluneJob.locked_at, None)
+                .set(RluneJob.claim, None)
+                .set(RluneJob.unique_key, None)
+                .set(RluneJob.last_error, Some(message))
+                .condition(claimed)
+                .await?
+        } else {
+            let exponent = u32::try_from(attempts.saturating_sub(1)).unwrap_or(0);
+            let delay = self
+                .retry_base_delay
+                .saturating_mul(2u32.saturating_pow(exponent))
+                .min(self.retry_max_delay);
+            warn!(
+                error.display = %error,
+                error.debug = ?error,
+                job.attempts = attempts,
+                "Job failed, retrying in {delay:?}"
+            );
+            rorm::update(&self.db, RluneJob)
+                .set(RluneJob.status, JobStatus::Pending)
+                .set(RluneJob.locked_at, None)
+                .set(RluneJob.claim, None)
+                .set(RluneJob.run_at, OffsetDateTime::now_utc() + delay)
+                .set(RluneJob.last_error, Some(message))
+                .condition(claimed)
+                .await?
+        };
+        if updated == 0 {
+            warn!("The job has been claimed by another worker while running");
+        }
+        Ok(())
+    }
+}
+
+impl Module for JobQueue {
+    type Setup = JobQueueSetup;
+    type PreInit = JobQueueSetup;
+
+    async fn pre_init(setup: Self::Setup) -> Result<Self::PreInit, PreInitError> {
+        Ok(setup)
+    }
+
+    type Dependencies = (Database,);
+
+    async fn init(setup: Self::PreInit, (db,): DependencyRefs<Self>) -> Result<Self, InitError> {
+        let JobQueueSetup {
+            workers,
+            poll_interval,
+            retry_base_delay,
+            retry_max_delay,
+            lease,
+            jobs,
+        } = setup;
+        Ok(Self {
+            db: db.clone(),
+            workers,
+            poll_interval,
+            retry_base_delay,
+            retry_max_delay,
+            lease,
+            jobs,
+            in_flight: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn post_init(&'static self) -> Result<(), PostInitError> {
+        // There is nothing this process could claim
+        if self.jobs.is_empty() {
+            return Ok(());
+        }
+
+        let tasks = Registry::global().tasks();
+        for index in 0..self.workers {
+            tasks.spawn(
+                format!("job-worker-{index}"),
+                RestartPolicy::OnPanic,
+                move |cancellation| self.work(cancellation),
+            );
+        }
+        Ok(())
+    }
+
+    /// Puts jobs back into the queue whose workers didn't finish during the graceful shutdown
+    async fn shutdown(&'static self) -> Result<(), ShutdownError> {
+        let in_flight = mem::take(
+            &mut *self
+                .in_flight
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        for (uuid, claim) in in_flight {
+            debug!(job.uuid = %uuid, "Releasing unfinished job");
+            (&self.db)
+                .execute::<Nothing>(
+                    format!(
+                        r#"UPDATE "{table}" SET "status" = 'Pending', "locked_at" = NULL, "claim" = NULL, "attempts" = "attempts" - 1 WHERE "uuid" = $1 AND "claim" = $2 AND "status" = 'Running';"#,
+                        table = RluneJob::TABLE,
+                    ),
+                    vec![SqlValue::Uuid(uuid), SqlValue::Uuid(claim)],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned by [`JobQueue::enqueue`]
+#[derive(Debug, Error)]
+pub enum JobQueueError {
+    #[error("Failed to serialize the job: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("Database error: {0}")]
+    Database(#[from] rorm::Error),
+}
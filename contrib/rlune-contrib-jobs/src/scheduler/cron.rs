@@ -0,0 +1,448 @@
+//! Parser and evaluator for cron expressions
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::LocalResult;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::TimeZone;
+use chrono::Timelike;
+use thiserror::Error;
+
+/// The number of years [`Cron::next_after`] searches before giving up
+///
+/// Expressions like `0 0 30 2 *` never match.
+const SEARCH_YEARS: i32 = 5;
+
+/// A parsed cron expression
+///
+/// The expression consists of the five fields `minute hour day-of-month month day-of-week`.
+/// Each field is `*`, a number, a range `a-b` or a comma separated list of them.
+/// `*` and ranges can be followed by a step `/n`.
+/// Months and weekdays may be written as their english three letter abbreviations.
+/// Both `0` and `7` are sunday.
+///
+/// Like in the traditional cron, if both day-of-month and day-of-week are restricted,
+/// a day matching either of them matches.
+///
+/// The shorthands `@yearly`, `@monthly`, `@weekly`, `@daily` and `@hourly` are supported as well.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cron {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether day-of-month and day-of-week are both restricted
+    either_day: bool,
+}
+
+impl Cron {
+    /// Gets the first point in time strictly after `after` matching the expression
+    ///
+    /// Like in cron, local times skipped by a daylight saving transition
+    /// match the first point in time after the transition.
+    /// Local times happening twice match at their first occurrence.
+    ///
+    /// Returns `None` if no such point in time exists within the next few years.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let timezone = after.timezone();
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let end_year = start.year() + SEARCH_YEARS;
+
+        let mut current = start;
+        while current.year() <= end_year {
+            if !contains(self.months, current.month()) {
+                let (year, month) = match current.month() {
+                    12 => (current.year() + 1, 1),
+                    month => (current.year(), month + 1),
+                };
+                current = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_day(current) {
+                current = current.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !contains(self.hours, current.hour()) {
+                current = current.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !contains(self.minutes, current.minute()) {
+                current += Duration::minutes(1);
+                continue;
+            }
+
+            match timezone.from_local_datetime(&current) {
+                LocalResult::Single(datetime) => return Some(datetime),
+                LocalResult::Ambiguous(earliest, _) => {
+                    if earliest > *after {
+                        return Some(earliest);
+                    }
+                }
+                LocalResult::None => return after_gap(&timezone, current),
+            }
+            current += Duration::minutes(1);
+        }
+        None
+    }
+
+    /// Checks the day-of-month and day-of-week fields
+    fn matches_day(&self, datetime: NaiveDateTime) -> bool {
+        let day_of_month = contains(self.days_of_month, datetime.day());
+        let day_of_week = contains(self.days_of_week, datetime.weekday().num_days_from_sunday());
+        if self.either_day {
+            day_of_month || day_of_week
+        } else {
+            day_of_month && day_of_week
+        }
+    }
+}
+
+impl FromStr for Cron {
+    type Err = CronError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let expanded = match source.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            expression => expression,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(CronError::FieldCount(fields.len()));
+        };
+
+        let mut days_of_week = parse_field(days_of_week, 0, 7, &WEEKDAYS)?;
+        // Both 0 and 7 are sunday
+        if contains(days_of_week, 7) {
+            days_of_week |= 1;
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            minutes: parse_field(minutes, 0, 59, &[])?,
+            hours: parse_field(hours, 0, 23, &[])?,
+            days_of_month: parse_field(days_of_month, 1, 31, &[])?,
+            months: parse_field(months, 1, 12, &MONTHS)?,
+            days_of_week,
+            either_day: !days_of_month.starts_with('*') && !fields[4].starts_with('*'),
+        })
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl fmt::Debug for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cron({:?})", self.source)
+    }
+}
+
+/// Error returned when parsing a [`Cron`] expression
+#[derive(Debug, Error)]
+pub enum CronError {
+    #[error("Expected 5 fields but found {0}")]
+    FieldCount(usize),
+
+    #[error("Invalid value '{0}'")]
+    InvalidValue(String),
+
+    #[error("Value '{value}' is outside of {min}-{max}")]
+    OutOfRange { value: u32, min: u32, max: u32 },
+
+    #[error("Invalid step '{0}'")]
+    InvalidStep(String),
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// The longest gap a timezone transition might skip
+///
+/// Samoa skipped an entire day when it moved across the date line.
+const MAX_GAP: Duration = Duration::days(2);
+
+/// Gets the first point in time after the gap containing the non-existent local time
+fn after_gap<Tz: TimeZone>(timezone: &Tz, skipped: NaiveDateTime) -> Option<DateTime<Tz>> {
+    let mut current = skipped;
+    while current - skipped <= MAX_GAP {
+        current += Duration::minutes(1);
+        match timezone.from_local_datetime(&current) {
+            LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+                return Some(datetime);
+            }
+            LocalResult::None => {}
+        }
+    }
+    None
+}
+
+/// Checks whether a bit set contains `value`
+fn contains(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parses a single field into a bit set
+///
+/// `names` are the names of the values starting at `min`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, CronError> {
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| CronError::InvalidStep(step.to_string()))?,
+            ),
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (
+                    parse_value(start, min, max, names)?,
+                    parse_value(end, min, max, names)?,
+                ),
+                // A step after a single value means "from the value to the end"
+                None if step > 1 => (parse_value(range, min, max, names)?, max),
+                None => {
+                    let value = parse_value(range, min, max, names)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(CronError::InvalidValue(range.to_string()));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+/// Parses a single value which is either a number or one of `names`
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, CronError> {
+    let number = match value.parse::<u32>() {
+        Ok(number) => number,
+        Err(_) => names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(value))
+            .map(|index| index as u32 + min)
+            .ok_or_else(|| CronError::InvalidValue(value.to_string()))?,
+    };
+    if number < min || number > max {
+        return Err(CronError::OutOfRange {
+            value: number,
+            min,
+            max,
+        });
+    }
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono::Utc;
+    use chrono_tz::Europe::Berlin;
+
+    use super::*;
+
+    fn cron(source: &str) -> Cron {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_fields() {
+        let cron = cron("*/15 9-17/4 1,15 jan-mar MON,fri");
+        assert_eq!(cron.minutes, 1 << 0 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(cron.hours, 1 << 9 | 1 << 13 | 1 << 17);
+        assert_eq!(cron.days_of_month, 1 << 1 | 1 << 15);
+        assert_eq!(cron.months, 1 << 1 | 1 << 2 | 1 << 3);
+        assert_eq!(cron.days_of_week, 1 << 1 | 1 << 5);
+        assert!(cron.either_day);
+    }
+
+    #[test]
+    fn parse_step_after_value() {
+        assert_eq!(cron("50/5 * * * *").minutes, 1 << 50 | 1 << 55);
+    }
+
+    #[test]
+    fn parse_sunday() {
+        assert_eq!(cron("0 0 * * 7").days_of_week, 1 | 1 << 7);
+        assert_eq!(cron("0 0 * * sun").days_of_week, 1);
+    }
+
+    #[test]
+    fn parse_shorthand() {
+        let daily = cron("@daily");
+        let expanded = cron("0 0 * * *");
+        assert_eq!(daily.minutes, expanded.minutes);
+        assert_eq!(daily.hours, expanded.hours);
+        assert_eq!(daily.days_of_month, expanded.days_of_month);
+        assert_eq!(daily.months, expanded.months);
+        assert_eq!(daily.days_of_week, expanded.days_of_week);
+        assert!(!daily.either_day);
+        assert_eq!(daily.to_string(), "@daily");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            "* * * *".parse::<Cron>(),
+            Err(CronError::FieldCount(4))
+        ));
+        assert!(matches!(
+            "60 * * * *".parse::<Cron>(),
+            Err(CronError::OutOfRange { value: 60, .. })
+        ));
+        assert!(matches!(
+            "0 0 0 * *".parse::<Cron>(),
+            Err(CronError::OutOfRange { value: 0, .. })
+        ));
+        assert!(matches!(
+            "*/0 * * * *".parse::<Cron>(),
+            Err(CronError::InvalidStep(_))
+        ));
+        assert!(matches!(
+            "5-1 * * * *".parse::<Cron>(),
+            Err(CronError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            "0 0 * foo *".parse::<Cron>(),
+            Err(CronError::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn next_is_strictly_after() {
+        let cron = cron("30 * * * *");
+        let after = Utc.with_ymd_and_hms(2024, 5, 1, 10, 10, 42).unwrap();
+        let next = Utc.with_ymd_and_hms(2024, 5, 1, 10, 30, 0).unwrap();
+        assert_eq!(cron.next_after(&after), Some(next));
+        assert_eq!(
+            cron.next_after(&next),
+            Some(Utc.with_ymd_and_hms(2024, 5, 1, 11, 30, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn next_crosses_year() {
+        let after = Utc.with_ymd_and_hms(2024, 12, 31, 23, 59, 0).unwrap();
+        assert_eq!(
+            cron("0 0 1 jan *").next_after(&after),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn next_either_day() {
+        // 2024-09-06 is the first friday before the 13th
+        let after = Utc.with_ymd_and_hms(2024, 9, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            cron("0 0 13 * fri").next_after(&after),
+            Some(Utc.with_ymd_and_hms(2024, 9, 6, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn next_never_matches() {
+        let after = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(cron("0 0 30 feb *").next_after(&after), None);
+    }
+
+    #[test]
+    fn next_runs_nonexistent_local_time_after_gap() {
+        // Berlin skips from 02:00 to 03:00 on 2024-03-31
+        let cron = cron("30 2 * * *");
+        let after = Berlin.with_ymd_and_hms(2024, 3, 30, 3, 0, 0).unwrap();
+        let shifted = cron.next_after(&after).unwrap();
+        assert_eq!(
+            shifted,
+            Berlin.with_ymd_and_hms(2024, 3, 31, 3, 0, 0).unwrap()
+        );
+        assert_eq!(
+            cron.next_after(&shifted),
+            Some(Berlin.with_ymd_and_hms(2024, 4, 1, 2, 30, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn next_runs_nonexistent_local_times_once() {
+        // Every time in the skipped hour is shifted to 03:00, which runs only once
+        let cron = cron("*/15 2 * * *");
+        let after = Berlin.with_ymd_and_hms(2024, 3, 31, 1, 59, 0).unwrap();
+        let shifted = cron.next_after(&after).unwrap();
+        assert_eq!(
+            shifted,
+            Berlin.with_ymd_and_hms(2024, 3, 31, 3, 0, 0).unwrap()
+        );
+        assert_eq!(
+            cron.next_after(&shifted),
+            Some(Berlin.with_ymd_and_hms(2024, 4, 1, 2, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn next_matches_repeated_local_time_once() {
+        // Berlin repeats 02:00 to 03:00 on 2024-10-27
+        let cron = cron("30 2 * * *");
+        let after = Berlin.with_ymd_and_hms(2024, 10, 27, 0, 0, 0).unwrap();
+        let first = cron.next_after(&after).unwrap();
+        assert_eq!(
+            first.with_timezone(&Utc),
+            Utc.with_ymd_and_hms(2024, 10, 27, 0, 30, 0).unwrap()
+        );
+
+        let second = cron.next_after(&first).unwrap();
+        assert_eq!(
+            second,
+            Berlin.with_ymd_and_hms(2024, 10, 28, 2, 30, 0).unwrap()
+        );
+
+        // Starting within the repeated hour doesn't match its first occurrence again
+        let repeated = Utc.with_ymd_and_hms(2024, 10, 27, 1, 15, 0).unwrap();
+        assert_eq!(
+            cron.next_after(&repeated.with_timezone(&Berlin)),
+            Some(second)
+        );
+    }
+
+    #[test]
+    fn next_hourly_across_dst() {
+        // The repeated local 02:00 only matches its first occurrence at 00:00 UTC
+        let cron = cron("0 * * * *");
+        let mut current = Utc
+            .with_ymd_and_hms(2024, 10, 26, 22, 30, 0)
+            .unwrap()
+            .with_timezone(&Berlin);
+        let mut utc_hours = Vec::new();
+        for _ in 0..4 {
+            current = cron.next_after(&current).unwrap();
+            utc_hours.push(current.with_timezone(&Utc).hour());
+        }
+        assert_eq!(utc_hours, [23, 0, 2, 3]);
+    }
+}
@@ -0,0 +1,47 @@
+//! Handlers exposing the [`Scheduler`]'s schedules and their run history
+//!
+//! They are not mounted automatically.
+//! Add [`router`] to your routes, preferably behind some authentication.
+
+use rlune_core::Module;
+use rlune_core::RluneRouter;
+use rlune_core::re_exports::axum::Json;
+use rlune_core::re_exports::axum::extract::Path;
+use rlune_core::re_exports::axum::extract::Query;
+use rlune_core::stuff::api_error::ApiError;
+use rlune_core::stuff::api_error::ApiResult;
+use rlune_macros::get;
+
+use crate::scheduler::Scheduler;
+use crate::scheduler::schema::RunsQuery;
+use crate::scheduler::schema::ScheduleInfo;
+use crate::scheduler::schema::ScheduleName;
+use crate::scheduler::schema::ScheduleRun;
+
+/// Constructs a router containing the scheduler's handlers
+pub fn router() -> RluneRouter {
+    RluneRouter::new()
+        .handler(get_schedules)
+        .handler(get_schedule_runs)
+}
+
+/// Lists all schedules with their next tick and most recent run
+#[get("/schedules", core_crate = "::rlune_core")]
+pub async fn get_schedules() -> ApiResult<Json<Vec<ScheduleInfo>>> {
+    Ok(Json(Scheduler::global().schedules().await?))
+}
+
+/// Lists a schedule's most recent runs
+#[get("/schedules/{name}/runs", core_crate = "::rlune_core")]
+pub async fn get_schedule_runs(
+    Path(ScheduleName { name }): Path<ScheduleName>,
+    Query(RunsQuery { limit }): Query<RunsQuery>,
+) -> ApiResult<Json<Vec<ScheduleRun>>> {
+    let scheduler = Scheduler::global();
+    if !scheduler.contains(&name) {
+        return Err(ApiError::bad_request("Unknown schedule"));
+    }
+    Ok(Json(
+        scheduler.runs(&name, limit.unwrap_or(50).min(1000)).await?,
+    ))
+}
@@ -0,0 +1,358 @@
+//! Recurring tasks run according to cron expressions
+//!
+//! ```ignore
+//! let mut setup = SchedulerSetup::default();
+//! setup.add("nightly-cleanup", "0 3 * * *", || async {
+//!     JobQueue::global()
+//!         .enqueue(&Cleanup, EnqueueOptions::default())
+//!         .await?;
+//!     Ok(())
+//! });
+//! rlune.register_module::<Scheduler>(setup);
+//! ```
+//!
+//! Every replica runs a scheduler, but each tick is run by a single one:
+//! The schedule's next tick is stored in the database
+//! and only the replica which manages to advance it runs the tick.
+//!
+//! If every replica was down during a tick, the schedule is run once when the first one comes back.
+
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::DateTime;
+use futures_concurrency::future::Join;
+use futures_lite::FutureExt;
+use futures_lite::future;
+use rlune_core::DependencyRefs;
+use rlune_core::InitError;
+use rlune_core::Module;
+use rlune_core::PostInitError;
+use rlune_core::PreInitError;
+use rlune_core::re_exports::uuid::Uuid;
+use rlune_core::registry::Registry;
+use rlune_core::registry::tasks::Cancellation;
+use rlune_core::registry::tasks::RestartPolicy;
+use rlune_core::registry::tasks::TaskError;
+use rlune_core::stuff::schema::SchemaDateTime;
+use rorm::Database;
+use time::OffsetDateTime;
+use tracing::Instrument;
+use tracing::debug;
+use tracing::error;
+use tracing::info_span;
+use tracing::warn;
+
+use crate::job::error_message;
+use crate::models::RluneSchedule;
+use crate::models::RluneScheduleRun;
+use crate::models::RunOutcome;
+use crate::scheduler::cron::Cron;
+use crate::scheduler::schema::ScheduleInfo;
+use crate::scheduler::schema::ScheduleRun;
+use crate::scheduler::setup::PreInit;
+use crate::scheduler::setup::Schedule;
+use crate::scheduler::setup::ScheduleSetup;
+pub use crate::scheduler::setup::SchedulerSetup;
+
+pub mod cron;
+pub mod handler;
+pub mod schema;
+mod setup;
+
+/// Module running recurring tasks
+///
+/// (see [module level docs](self))
+pub struct Scheduler {
+    db: Database,
+    poll_interval: Duration,
+    schedules: Vec<Schedule>,
+}
+
+impl Schedule {
+    /// Calculates the first tick after `now`
+    fn next_after(&self, now: OffsetDateTime) -> OffsetDateTime {
+        DateTime::from_timestamp(now.unix_timestamp(), 0)
+            .and_then(|now| self.cron.next_after(&now.with_timezone(&self.timezone)))
+            .and_then(|next| OffsetDateTime::from_unix_timestamp(next.timestamp()).ok())
+            // Expressions which never match are rejected in `pre_init`
+            .unwrap_or(now + time::Duration::weeks(52))
+    }
+}
+
+impl Scheduler {
+    /// Checks whether a schedule with this name has been added
+    pub fn contains(&self, name: &str) -> bool {
+        self.schedules.iter().any(|schedule| schedule.name == name)
+    }
+
+    /// Lists all schedules with their next tick and most recent run
+    pub async fn schedules(&self) -> Result<Vec<ScheduleInfo>, rorm::Error> {
+        let mut infos = Vec::with_capacity(self.schedules.len());
+        for schedule in &self.schedules {
+            let next_run_at = rorm::query(&self.db, RluneSchedule.next_run_at)
+                .condition(RluneSchedule.name.equals(schedule.name.as_str()))
+                .optional()
+                .await?;
+            let last_run = self.runs(&schedule.name, 1).await?.into_iter().next();
+            infos.push(ScheduleInfo {
+                name: schedule.name.clone(),
+                cron: schedule.cron.to_string(),
+                timezone: schedule.timezone.name().to_string(),
+                next_run_at: next_run_at.map(SchemaDateTime),
+                last_run,
+            });
+        }
+        Ok(infos)
+    }
+
+    /// Lists a schedule's most recent runs
+    pub async fn runs(&self, name: &str, limit: u64) -> Result<Vec<ScheduleRun>, rorm::Error> {
+        let runs = rorm::query(&self.db, RluneScheduleRun)
+            .condition(RluneScheduleRun.schedule.equals(name))
+            .order_desc(RluneScheduleRun.started_at)
+            .limit(limit)
+            .all()
+            .await?;
+        Ok(runs.into_iter().map(ScheduleRun::from).collect())
+    }
+
+    /// The loop checking for due schedules
+    async fn work(&'static self, cancellation: Cancellation) -> Result<(), TaskError> {
+        while !cancellation.is_cancelled() {
+            let next_tick = self.tick().await.unwrap_or_else(|error| {
+                warn!(
+                    error.display = %error,
+                    error.debug = ?error,
+                    "Failed to check schedules"
+                );
+                None
+            });
+
+            let sleep = next_tick
+                .map(|next_tick| {
+                    Duration::try_from(next_tick - OffsetDateTime::now_utc())
+                        .unwrap_or(Duration::ZERO)
+                })
+                .unwrap_or(self.poll_interval)
+                .min(self.poll_interval);
+            future::or(cancellation.cancelled(), tokio::time::sleep(sleep)).await;
+        }
+        Ok(())
+    }
+
+    /// Runs every due schedule this replica managed to claim
+    ///
+    /// Returns the earliest next tick.
+    async fn tick(&self) -> Result<Option<OffsetDateTime>, rorm::Error> {
+        let now = OffsetDateTime::now_utc();
+        let mut due = Vec::new();
+        let mut next_tick: Option<OffsetDateTime> = None;
+        for schedule in &self.schedules {
+            let (claimed, next) = self.claim(schedule, now).await?;
+            if claimed {
+                due.push(schedule);
+            }
+            next_tick = Some(next_tick.map_or(next, |next_tick| next_tick.min(next)));
+        }
+
+        due.into_iter()
+            .map(|schedule| {
+                self.execute(schedule)
+                    .instrument(info_span!("schedule", schedule.name = %schedule.name))
+            })
+            .collect::<Vec<_>>()
+            .join()
+            .await;
+
+        Ok(next_tick)
+    }
+
+    /// Tries to claim a schedule's tick if it is due
+    ///
+    /// Returns whether the tick has been claimed and the schedule's next tick.
+    async fn claim(
+        &self,
+        schedule: &Schedule,
+        now: OffsetDateTime,
+    ) -> Result<(bool, OffsetDateTime), rorm::Error> {
+        let next = schedule.next_after(now);
+        let cron = schedule.cron.to_string();
+        let timezone = schedule.timezone.name();
+
+        let stored = rorm::query(&self.db, RluneSchedule)
+            .condition(RluneSchedule.name.equals(schedule.name.as_str()))
+            .optional()
+            .await?;
+        let Some(stored) = stored else {
+            let inserted = rorm::insert(&self.db, RluneSchedule)
+                .return_nothing()
+                .single(&RluneSchedule {
+                    name: schedule.name.clone(),
+                    cron,
+                    timezone: timezone.to_string(),
+                    next_run_at: next,
+                })
+                .await;
+            if let Err(error) = inserted {
+                // Most likely another replica inserted it concurrently
+                debug!(error.display = %error, "Failed to insert schedule");
+            }
+            return Ok((false, next));
+        };
+
+        if stored.cron != cron || stored.timezone != timezone {
+            debug!(
+                schedule.name = %schedule.name,
+                "Schedule changed, recalculating its next tick"
+            );
+            rorm::update(&self.db, RluneSchedule)
+                .set(RluneSchedule.cron, cron)
+                .set(RluneSchedule.timezone, timezone.to_string())
+                .set(RluneSchedule.next_run_at, next)
+                .condition(rorm::and![
+                    RluneSchedule.name.equals(schedule.name.as_str()),
+                    RluneSchedule.next_run_at.equals(stored.next_run_at),
+                ])
+                .await?;
+            return Ok((false, next));
+        }
+
+        if stored.next_run_at > now {
+            return Ok((false, stored.next_run_at));
+        }
+
+        let updated = rorm::update(&self.db, RluneSchedule)
+            .set(RluneSchedule.next_run_at, next)
+            .condition(rorm::and![
+                RluneSchedule.name.equals(schedule.name.as_str()),
+                RluneSchedule.next_run_at.equals(stored.next_run_at),
+            ])
+            .await?;
+        Ok((updated > 0, next))
+    }
+
+    /// Runs a schedule and records the run
+    async fn execute(&self, schedule: &Schedule) {
+        debug!("Running schedule");
+        let started_at = OffsetDateTime::now_utc();
+        let start = Instant::now();
+        let result = AssertUnwindSafe((schedule.task)())
+            .catch_unwind()
+            .await
+            .unwrap_or_else(|_| Err("The scheduled task panicked".into()));
+        let duration = start.elapsed();
+
+        let (outcome, error) = match result {
+            Ok(()) => (RunOutcome::Success, None),
+            Err(error) => {
+                warn!(
+                    error.display = %error,
+                    error.debug = ?error,
+                    "Scheduled task failed"
+                );
+                (RunOutcome::Failure, Some(error_message(&error)))
+            }
+        };
+
+        let recorded = rorm::insert(&self.db, RluneScheduleRun)
+            .return_nothing()
+            .single(&RluneScheduleRun {
+                uuid: Uuid::new_v4(),
+                schedule: schedule.name.clone(),
+                started_at,
+                duration_ms: i64::try_from(duration.as_millis()).unwrap_or(i64::MAX),
+                outcome,
+                error,
+            })
+            .await;
+        if let Err(error) = recorded {
+            error!(
+                error.display = %error,
+                error.debug = ?error,
+                "Failed to record schedule run"
+            );
+        }
+    }
+}
+
+impl Module for Scheduler {
+    type Setup = SchedulerSetup;
+    type PreInit = PreInit;
+
+    async fn pre_init(setup: Self::Setup) -> Result<Self::PreInit, PreInitError> {
+        let SchedulerSetup {
+            timezone,
+            poll_interval,
+            schedules,
+        } = setup;
+
+        let mut parsed = Vec::with_capacity(schedules.len());
+        for ScheduleSetup {
+            name,
+            cron,
+            timezone: schedule_timezone,
+            task,
+        } in schedules
+        {
+            if parsed
+                .iter()
+                .any(|schedule: &Schedule| schedule.name == name)
+            {
+                return Err(format!("The schedule '{name}' has been added twice").into());
+            }
+
+            let cron: Cron = cron.parse().map_err(|error| {
+                format!("Invalid cron expression of schedule '{name}': {error}")
+            })?;
+            let timezone = schedule_timezone.unwrap_or(timezone);
+            if cron
+                .next_after(&chrono::Utc::now().with_timezone(&timezone))
+                .is_none()
+            {
+                return Err(
+                    format!("The cron expression of schedule '{name}' never matches").into(),
+                );
+            }
+
+            parsed.push(Schedule {
+                name,
+                cron,
+                timezone,
+                task,
+            });
+        }
+        Ok(PreInit {
+            poll_interval,
+            schedules: parsed,
+        })
+    }
+
+    type Dependencies = (Database,);
+
+    async fn init(
+        PreInit {
+            poll_interval,
+            schedules,
+        }: Self::PreInit,
+        (db,): DependencyRefs<Self>,
+    ) -> Result<Self, InitError> {
+        Ok(Self {
+            db: db.clone(),
+            poll_interval,
+            schedules,
+        })
+    }
+
+    async fn post_init(&'static self) -> Result<(), PostInitError> {
+        if !self.schedules.is_empty() {
+            Registry::global().tasks().spawn(
+                "scheduler",
+                RestartPolicy::OnPanic,
+                move |cancellation| self.work(cancellation),
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use rlune_core::re_exports::uuid::Uuid;
+use rlune_core::stuff::schema::SchemaDateTime;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::models::RluneScheduleRun;
+use crate::models::RunOutcome;
+
+/// A schedule known to the [`Scheduler`](crate::scheduler::Scheduler)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleInfo {
+    /// The schedule's name
+    pub name: String,
+
+    /// The schedule's cron expression
+    pub cron: String,
+
+    /// The timezone the cron expression is evaluated in
+    pub timezone: String,
+
+    /// The next tick
+    ///
+    /// `None` if no replica picked up the schedule yet.
+    pub next_run_at: Option<SchemaDateTime>,
+
+    /// The most recent run
+    pub last_run: Option<ScheduleRun>,
+}
+
+/// A single run of a schedule
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleRun {
+    /// The run's unique identifier
+    pub uuid: Uuid,
+
+    /// The point in time the run started
+    pub started_at: SchemaDateTime,
+
+    /// The run's duration in milliseconds
+    pub duration_ms: i64,
+
+    /// Whether the run succeeded
+    pub outcome: RunOutcome,
+
+    /// The error of a failed run
+    pub error: Option<String>,
+}
+
+impl From<RluneScheduleRun> for ScheduleRun {
+    fn from(value: RluneScheduleRun) -> Self {
+        Self {
+            uuid: value.uuid,
+            started_at: SchemaDateTime(value.started_at),
+            duration_ms: value.duration_ms,
+            outcome: value.outcome,
+            error: value.error,
+        }
+    }
+}
+
+/// Path parameter selecting a schedule
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleName {
+    /// The schedule's name
+    pub name: String,
+}
+
+/// Query parameters for listing a schedule's runs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RunsQuery {
+    /// The maximum number of runs to return
+    ///
+    /// Defaults to 50.
+    pub limit: Option<u64>,
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use chrono_tz::Tz;
+use futures_lite::future;
+
+use crate::job::JobError;
+use crate::scheduler::cron::Cron;
+
+/// Type erased function starting a run of a schedule
+pub(crate) type ScheduledTask = Box<dyn Fn() -> future::Boxed<Result<(), JobError>> + Send + Sync>;
+
+/// Setup for the [`Scheduler`](crate::scheduler::Scheduler)
+pub struct SchedulerSetup {
+    /// The timezone cron expressions are evaluated in unless specified otherwise
+    ///
+    /// Defaults to UTC.
+    pub timezone: Tz,
+
+    /// The maximum time the scheduler sleeps between checking for due schedules
+    pub poll_interval: Duration,
+
+    pub(crate) schedules: Vec<ScheduleSetup>,
+}
+
+/// A schedule added to the [`SchedulerSetup`]
+pub(crate) struct ScheduleSetup {
+    pub name: String,
+    pub cron: String,
+    pub timezone: Option<Tz>,
+    pub task: ScheduledTask,
+}
+
+/// A parsed schedule
+pub struct Schedule {
+    pub(crate) name: String,
+    pub(crate) cron: Cron,
+    pub(crate) timezone: Tz,
+    pub(crate) task: ScheduledTask,
+}
+
+/// The [`SchedulerSetup`] after parsing its cron expressions
+pub struct PreInit {
+    pub(crate) poll_interval: Duration,
+    pub(crate) schedules: Vec<Schedule>,
+}
+
+impl SchedulerSetup {
+    /// Adds a schedule running `task` whenever the cron expression matches
+    ///
+    /// The expression is parsed by the [`Cron`](crate::scheduler::cron::Cron) parser
+    /// when the scheduler is initialized.
+    ///
+    /// The `name` identifies the schedule across restarts and replicas, so it should never change.
+    ///
+    /// Runs should be short. Heavy work should be enqueued as [`Job`](crate::Job) instead.
+    pub fn add<Fut>(
+        &mut self,
+        name: impl Into<String>,
+        cron: impl Into<String>,
+        task: impl Fn() -> Fut + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        Fut: Future<Output = Result<(), JobError>> + Send + 'static,
+    {
+        self.push(name.into(), cron.into(), None, task)
+    }
+
+    /// Adds a schedule whose cron expression is evaluated in a specific timezone
+    ///
+    /// See [`SchedulerSetup::add`].
+    pub fn add_in_timezone<Fut>(
+        &mut self,
+        name: impl Into<String>,
+        cron: impl Into<String>,
+        timezone: Tz,
+        task: impl Fn() -> Fut + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        Fut: Future<Output = Result<(), JobError>> + Send + 'static,
+    {
+        self.push(name.into(), cron.into(), Some(timezone), task)
+    }
+
+    fn push<Fut>(
+        &mut self,
+        name: String,
+        cron: String,
+        timezone: Option<Tz>,
+        task: impl Fn() -> Fut + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        Fut: Future<Output = Result<(), JobError>> + Send + 'static,
+    {
+        self.schedules.push(ScheduleSetup {
+            name,
+            cron,
+            timezone,
+            task: Box::new(move || Box::pin(task())),
+        });
+        self
+    }
+}
+
+impl Default for SchedulerSetup {
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            poll_interval: Duration::from_secs(30),
+            schedules: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Debug for SchedulerSetup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchedulerSetup")
+            .field("timezone", &self.timezone)
+            .field("poll_interval", &self.poll_interval)
+            .field(
+                "schedules",
+                &self
+                    .schedules
+                    .iter()
+                    .map(|schedule| (&schedule.name, &schedule.cron, &schedule.timezone))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::job::Job;
+use crate::job::JobRunner;
+use crate::job::run_job;
+
+/// Setup for the [`JobQueue`](crate::JobQueue)
+#[derive(Debug)]
+pub struct JobQueueSetup {
+    /// The number of jobs processed concurrently by this process
+    ///
+    /// Set to `0` to only enqueue jobs and leave processing to other processes.
+    pub workers: usize,
+
+    /// The time an idle worker waits before checking for new jobs
+    pub poll_interval: Duration,
+
+    /// The delay before the first retry of a failed job
+    ///
+    /// It is doubled for every further attempt.
+    pub retry_base_delay: Duration,
+
+    /// The maximum delay between two attempts of a failed job
+    pub retry_max_delay: Duration,
+
+    /// The time after which a running job is considered abandoned (i.e. its process crashed)
+    /// and is claimed again
+    ///
+    /// Workers renew the lease of their jobs every third of this duration while they are running.
+    pub lease: Duration,
+
+    /// The runners of the registered jobs by their kind
+    pub(crate) jobs: HashMap<&'static str, JobRunner>,
+}
+
+impl JobQueueSetup {
+    /// Registers a type of job to be processed by this process's workers
+    ///
+    /// The workers only claim jobs of registered types.
+    /// Without any registered job, no worker is started.
+    ///
+    /// # Panics
+    /// If another job with the same [`Job::KIND`] has already been registered.
+    pub fn register<J: Job>(&mut self) -> &mut Self {
+        if self.jobs.insert(J::KIND, run_job::<J>).is_some() {
+            panic!("A job of kind '{}' has already been registered", J::KIND);
+        }
+        self
+    }
+}
+
+impl Default for JobQueueSetup {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            poll_interval: Duration::from_secs(1),
+            retry_base_delay: Duration::from_secs(10),
+            retry_max_delay: Duration::from_secs(60 * 60),
+            lease: Duration::from_secs(15 * 60),
+            jobs: HashMap::new(),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use rlune_core::audit::AuditEvent;
+use rlune_core::audit::AuditOutcome;
+use rlune_core::re_exports::uuid::Uuid;
+
+/// Security relevant events recorded by the [`OauthProviderModule`](crate::OauthProviderModule)
+#[derive(Debug, Clone)]
+pub enum OauthAuditEvent {
+    /// A user granted a client access
+    Grant {
+        /// The logged-in account's primary key
+        account: Option<i64>,
+        /// The client's uuid
+        client: Uuid,
+    },
+
+    /// A user denied a client access
+    Deny {
+        /// The logged-in account's primary key
+        account: Option<i64>,
+        /// The client's uuid
+        client: Uuid,
+    },
+}
+
+impl From<OauthAuditEvent> for AuditEvent {
+    fn from(value: OauthAuditEvent) -> Self {
+        let (kind, account, client) = match value {
+            OauthAuditEvent::Grant { account, client } => ("grant", account, client),
+            OauthAuditEvent::Deny { account, client } => ("deny", account, client),
+        };
+        let event = AuditEvent::new("oauth", kind, AuditOutcome::Success)
+            .detail("client", client.to_string());
+        match account {
+            Some(account) => event.subject(account),
+            None => event,
+        }
+    }
+}
@@ -1,16 +1,20 @@
 use rlune_core::Module;
+use rlune_core::re_exports::axum::Extension;
 use rlune_core::re_exports::axum::extract::Path;
 use rlune_core::re_exports::axum::extract::Query;
 use rlune_core::re_exports::axum::response::Redirect;
 use rlune_core::re_exports::uuid::Uuid;
+use rlune_core::session::Session;
 use rlune_core::stuff::api_error::ApiError;
 use rlune_core::stuff::api_error::ApiResult;
 use rlune_core::stuff::schema::SingleUuid;
 use rlune_macros::get;
 use tracing::info;
+use tracing::warn;
 use url::Url;
 
 use crate::OauthProviderModule;
+use crate::audit::OauthAuditEvent;
 use crate::handler::error::OauthErrorBuilder;
 use crate::handler::error::OauthResult;
 use crate::handler::schema::AuthErrorType;
@@ -108,7 +112,6 @@ pub async fn auth(Query(request): Query<AuthRequest>) -> OauthResult<Redirect> {
         code_challenge,
     });
     let frontend_redirect = OauthProviderModule::global()
-        .setup
         .frontend_redirect
         .redirect_uri(request_uuid);
 
@@ -118,12 +121,23 @@ pub async fn auth(Query(request): Query<AuthRequest>) -> OauthResult<Redirect> {
 
 /// Endpoint visited by user to grant a requesting application access
 #[get("/accept/{uuid}", core_crate = "::rlune_core")]
-pub async fn accept(path: Path<SingleUuid>) -> ApiResult<Redirect> {
+pub async fn accept(
+    session: Option<Extension<Session>>,
+    path: Path<SingleUuid>,
+) -> ApiResult<Redirect> {
     let open_request = OauthProviderModule::global()
         .remove_open(path.uuid)
         .ok_or(ApiError::bad_request("Invalid oauth request uuid"))?;
     let response_uuid = OauthProviderModule::global().insert_accepted(open_request.clone());
 
+    OauthProviderModule::global()
+        .audit
+        .record(OauthAuditEvent::Grant {
+            account: current_account(session).await,
+            client: open_request.client_uuid,
+        })
+        .await;
+
     let redirect_uri = rorm::query(
         &OauthProviderModule::global().db,
         RluneOauthClient.redirect_uri,
@@ -147,11 +161,22 @@ pub async fn accept(path: Path<SingleUuid>) -> ApiResult<Redirect> {
 
 /// Endpoint visited by user to deny a requesting application access
 #[get("/deny/{uuid}", core_crate = "::rlune_core")]
-pub async fn deny(path: Path<SingleUuid>) -> ApiResult<Redirect> {
+pub async fn deny(
+    session: Option<Extension<Session>>,
+    path: Path<SingleUuid>,
+) -> ApiResult<Redirect> {
     let open_request = OauthProviderModule::global()
         .remove_open(path.uuid)
         .ok_or(ApiError::bad_request("Invalid oauth request uuid"))?;
 
+    OauthProviderModule::global()
+        .audit
+        .record(OauthAuditEvent::Deny {
+            account: current_account(session).await,
+            client: open_request.client_uuid,
+        })
+        .await;
+
     let redirect_uri = rorm::query(
         &OauthProviderModule::global().db,
         RluneOauthClient.redirect_uri,
@@ -172,3 +197,18 @@ pub async fn deny(path: Path<SingleUuid>) -> ApiResult<Redirect> {
 
     Ok(Redirect::temporary(redirect_uri.as_str()))
 }
+
+/// Gets the logged-in account to record in the audit events
+///
+/// The endpoints don't require a login, so this is `None` without one.
+async fn current_account(session: Option<Extension<Session>>) -> Option<i64> {
+    let Extension(session) = session?;
+    session.get("account").await.unwrap_or_else(|error| {
+        warn!(
+            error.display = %error,
+            error.debug = ?error,
+            "Failed to read the session"
+        );
+        None
+    })
+}
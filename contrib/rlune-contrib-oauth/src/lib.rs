@@ -1,3 +1,4 @@
+pub mod audit;
 pub mod handler;
 mod models;
 pub(crate) mod module;
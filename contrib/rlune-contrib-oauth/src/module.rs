@@ -2,18 +2,23 @@ use std::collections::HashMap;
 use std::sync::Mutex;
 use std::sync::PoisonError;
 
+use rlune_core::DependencyRefs;
 use rlune_core::InitError;
 use rlune_core::Module;
 use rlune_core::PreInitError;
+use rlune_core::audit::AuditLog;
 use rlune_core::re_exports::rorm::Database;
 use rlune_core::re_exports::uuid::Uuid;
 
 use crate::OauthProviderSetup;
+use crate::setup::FrontendRedirect;
 
 pub struct OauthProviderModule {
     pub(crate) db: Database,
 
-    pub(crate) setup: OauthProviderSetup,
+    pub(crate) frontend_redirect: Box<dyn FrontendRedirect>,
+
+    pub(crate) audit: AuditLog,
 
     /// Waiting for user interaction i.e. `/accept` or `/deny`
     ///
@@ -85,13 +90,15 @@ impl Module for OauthProviderModule {
 
     type Dependencies = (Database,);
 
-    async fn init(
-        pre_init: Self::PreInit,
-        (db,): &mut Self::Dependencies,
-    ) -> Result<Self, InitError> {
+    async fn init(pre_init: Self::PreInit, (db,): DependencyRefs<Self>) -> Result<Self, InitError> {
+        let OauthProviderSetup {
+            frontend_redirect,
+            audit_sink,
+        } = pre_init.setup;
         Ok(Self {
             db: db.clone(),
-            setup: pre_init.setup,
+            frontend_redirect,
+            audit: AuditLog::new(audit_sink),
             open_requests: Mutex::new(HashMap::new()),
             accepted_requests: Mutex::new(HashMap::new()),
         })
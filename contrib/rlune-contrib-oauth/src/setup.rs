@@ -1,17 +1,23 @@
 use std::fmt;
 
+use rlune_core::audit::AuditSink;
+use rlune_core::audit::DatabaseAuditSink;
 use rlune_core::re_exports::uuid::Uuid;
 
 /// Setup for the [`OauthProviderModule`](crate::OauthProviderModule)
 #[derive(Debug)]
 pub struct OauthProviderSetup {
     pub frontend_redirect: Box<dyn FrontendRedirect>,
+
+    /// Where to record the module's [`OauthAuditEvent`](crate::audit::OauthAuditEvent)s
+    pub audit_sink: Box<dyn AuditSink>,
 }
 
 impl Default for OauthProviderSetup {
     fn default() -> Self {
         Self {
             frontend_redirect: Box::new(DefaultFrontendRedirect),
+            audit_sink: Box::new(DatabaseAuditSink),
         }
     }
 }
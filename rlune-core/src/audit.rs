@@ -0,0 +1,316 @@
+//! Persistent trail of security relevant events
+//!
+//! Modules record [`AuditEvent`]s through an [`AuditLog`]
+//! which forwards them to a configurable [`AuditSink`].
+//!
+//! This crate provides three sinks:
+//! - [`DatabaseAuditSink`] stores events in the [`RluneAuditEvent`] table and can be queried
+//! - [`TracingAuditSink`] emits events as tracing events with the target `audit`
+//! - [`FileAuditSink`] appends events as json lines to a file
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use async_trait::async_trait;
+use rorm::Database;
+use rorm::DbEnum;
+use rorm::Model;
+use rorm::fields::types::Json;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::Module;
+use crate::stuff::schema::GetPageRequest;
+use crate::stuff::schema::Page;
+use crate::stuff::schema::SchemaDateTime;
+
+/// Error returned by an [`AuditSink`]
+pub type AuditSinkError = Box<dyn Error + Send + Sync + 'static>;
+
+/// A single security relevant event
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEvent {
+    /// The event's unique identifier
+    pub uuid: Uuid,
+
+    /// The point in time the event occurred at
+    pub occurred_at: SchemaDateTime,
+
+    /// The module which recorded the event (for example `auth` or `oauth`)
+    pub source: String,
+
+    /// The kind of event (for example `login` or `logout`)
+    pub kind: String,
+
+    /// Whether the action described by the event succeeded
+    pub outcome: AuditOutcome,
+
+    /// The account (or client) the event is about, if known
+    pub subject: Option<String>,
+
+    /// Arbitrary additional information (for example the used login method)
+    pub details: Map<String, Value>,
+}
+
+impl AuditEvent {
+    /// Constructs a new `AuditEvent` which occurred just now
+    pub fn new(source: &str, kind: &str, outcome: AuditOutcome) -> Self {
+        Self {
+            uuid: Uuid::new_v4(),
+            occurred_at: SchemaDateTime(OffsetDateTime::now_utc()),
+            source: source.to_string(),
+            kind: kind.to_string(),
+            outcome,
+            subject: None,
+            details: Map::new(),
+        }
+    }
+
+    /// Sets the event's subject
+    pub fn subject(mut self, subject: impl fmt::Display) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// Adds a key value pair to the event's details
+    pub fn detail(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.details.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+/// Whether the action described by an [`AuditEvent`] succeeded
+#[derive(DbEnum, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum AuditOutcome {
+    /// The action succeeded
+    Success,
+    /// The action failed or was rejected
+    Failure,
+}
+
+impl AuditOutcome {
+    /// Converts a `Result` into an `AuditOutcome`
+    pub fn of<T, E>(result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => Self::Success,
+            Err(_) => Self::Failure,
+        }
+    }
+}
+
+/// Receives [`AuditEvent`]s recorded through an [`AuditLog`]
+///
+/// A module which records events should accept a `Box<dyn AuditSink>` in its setup.
+#[async_trait]
+pub trait AuditSink: fmt::Debug + Send + Sync + 'static {
+    /// Persists a single event
+    async fn record(&self, event: &AuditEvent) -> Result<(), AuditSinkError>;
+}
+
+/// Handle used by modules to record [`AuditEvent`]s
+#[derive(Debug)]
+pub struct AuditLog {
+    sink: Box<dyn AuditSink>,
+}
+
+impl AuditLog {
+    /// Constructs a new `AuditLog` which forwards events to `sink`
+    pub fn new(sink: Box<dyn AuditSink>) -> Self {
+        Self { sink }
+    }
+
+    /// Records an event
+    ///
+    /// Failing to record an event won't fail the calling operation.
+    /// Instead, the error and the event will be logged.
+    pub async fn record(&self, event: impl Into<AuditEvent>) {
+        let event = event.into();
+        if let Err(error) = self.sink.record(&event).await {
+            error!(
+                audit.source = event.source,
+                audit.kind = event.kind,
+                audit.subject = event.subject,
+                error.display = %error,
+                error.debug = ?error,
+                "Failed to record audit event"
+            );
+        }
+    }
+}
+
+/// The database table storing [`AuditEvent`]s written by the [`DatabaseAuditSink`]
+#[derive(Model)]
+pub struct RluneAuditEvent {
+    /// The event's unique identifier
+    #[rorm(primary_key)]
+    pub uuid: Uuid,
+
+    /// The point in time the event occurred at
+    pub occurred_at: OffsetDateTime,
+
+    /// The module which recorded the event
+    #[rorm(max_length = 255)]
+    pub source: String,
+
+    /// The kind of event
+    #[rorm(max_length = 255)]
+    pub kind: String,
+
+    /// Whether the action described by the event succeeded
+    pub outcome: AuditOutcome,
+
+    /// The account (or client) the event is about, if known
+    #[rorm(max_length = 255)]
+    pub subject: Option<String>,
+
+    /// Arbitrary additional information
+    pub details: Json<Map<String, Value>>,
+}
+
+impl From<RluneAuditEvent> for AuditEvent {
+    fn from(value: RluneAuditEvent) -> Self {
+        Self {
+            uuid: value.uuid,
+            occurred_at: SchemaDateTime(value.occurred_at),
+            source: value.source,
+            kind: value.kind,
+            outcome: value.outcome,
+            subject: value.subject,
+            details: value.details.into_inner(),
+        }
+    }
+}
+
+/// [`AuditSink`] storing events in the [`RluneAuditEvent`] table
+///
+/// It uses the global [`Database`] module, so it requires it to be registered.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DatabaseAuditSink;
+
+impl DatabaseAuditSink {
+    /// Queries a page of recorded events, newest first
+    pub async fn query(
+        db: &Database,
+        page: GetPageRequest,
+    ) -> Result<Page<AuditEvent>, rorm::Error> {
+        let mut tx = db.start_transaction().await?;
+
+        let total = rorm::query(&mut tx, RluneAuditEvent.uuid.count())
+            .one()
+            .await?;
+
+        let items = rorm::query(&mut tx, RluneAuditEvent)
+            .order_desc(RluneAuditEvent.occurred_at)
+            .limit(page.limit)
+            .offset(page.offset)
+            .all()
+            .await?
+            .into_iter()
+            .map(AuditEvent::from)
+            .collect();
+
+        tx.commit().await?;
+        Ok(Page {
+            items,
+            limit: page.limit,
+            offset: page.offset,
+            total,
+        })
+    }
+}
+
+#[async_trait]
+impl AuditSink for DatabaseAuditSink {
+    async fn record(&self, event: &AuditEvent) -> Result<(), AuditSinkError> {
+        rorm::insert(Database::global(), RluneAuditEvent)
+            .return_nothing()
+            .single(&RluneAuditEvent {
+                uuid: event.uuid,
+                occurred_at: event.occurred_at.0,
+                source: event.source.clone(),
+                kind: event.kind.clone(),
+                outcome: event.outcome,
+                subject: event.subject.clone(),
+                details: Json(event.details.clone()),
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+/// [`AuditSink`] emitting events as tracing events with the target `audit`
+///
+/// Successful actions are logged with level `INFO`, failed ones with `WARN`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TracingAuditSink;
+
+#[async_trait]
+impl AuditSink for TracingAuditSink {
+    async fn record(&self, event: &AuditEvent) -> Result<(), AuditSinkError> {
+        let details = Value::Object(event.details.clone());
+        match event.outcome {
+            AuditOutcome::Success => info!(
+                target: "audit",
+                audit_uuid = %event.uuid,
+                audit_source = event.source,
+                audit_kind = event.kind,
+                audit_subject = event.subject,
+                audit_details = %details,
+                "Audit event"
+            ),
+            AuditOutcome::Failure => warn!(
+                target: "audit",
+                audit_uuid = %event.uuid,
+                audit_source = event.source,
+                audit_kind = event.kind,
+                audit_subject = event.subject,
+                audit_details = %details,
+                "Audit event"
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// [`AuditSink`] appending events as json lines to a file
+#[derive(Debug)]
+pub struct FileAuditSink {
+    file: Mutex<File>,
+}
+
+impl FileAuditSink {
+    /// Opens (or creates) the file at `path` to append events to it
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            file: Mutex::new(OpenOptions::new().create(true).append(true).open(path)?),
+        })
+    }
+}
+
+#[async_trait]
+impl AuditSink for FileAuditSink {
+    async fn record(&self, event: &AuditEvent) -> Result<(), AuditSinkError> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        file.write_all(&line)?;
+        file.flush()?;
+        Ok(())
+    }
+}
@@ -10,9 +10,11 @@ pub mod re_exports {
     pub use uuid;
 }
 
+pub mod audit;
 pub mod handler;
 #[doc(hidden)]
 pub mod macro_utils;
+pub mod metrics;
 pub mod module;
 #[doc(hidden)]
 pub mod router;
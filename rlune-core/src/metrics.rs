@@ -0,0 +1,403 @@
+//! Metrics exported in Prometheus' text format
+//!
+//! rlune records metrics about the requests it handles.
+//! Modules can register their own counters, gauges and histograms:
+//!
+//! ```ignore
+//! let finished = Metrics::global().counter(
+//!     "jobs_finished_total",
+//!     "Number of jobs which have finished",
+//!     &[("queue", "default")],
+//! );
+//! finished.inc();
+//! ```
+//!
+//! A metric is identified by its name and labels:
+//! registering the same name and labels again returns the already registered metric.
+//! Metrics can therefore be looked up where they are needed instead of being stored.
+//!
+//! Values which are cheap to compute but expensive to track can be registered
+//! as [`gauge_fn`](Metrics::gauge_fn) which is called whenever the metrics are rendered.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// The default buckets for histograms measuring durations in seconds
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The metrics of this process
+static GLOBAL: Metrics = Metrics {
+    families: Mutex::new(BTreeMap::new()),
+};
+
+/// The registry of all metrics
+///
+/// (see [module level docs](self))
+pub struct Metrics {
+    families: Mutex<BTreeMap<&'static str, Family>>,
+}
+
+/// All metrics sharing a name
+struct Family {
+    help: &'static str,
+
+    /// The type as written in the `# TYPE` line
+    kind: &'static str,
+
+    series: BTreeMap<Labels, Series>,
+}
+
+/// The labels distinguishing metrics sharing a name
+type Labels = Vec<(&'static str, String)>;
+
+/// A single metric
+#[derive(Clone)]
+enum Series {
+    Counter(Counter),
+    Gauge(Gauge),
+    GaugeFn(Arc<dyn Fn() -> i64 + Send + Sync>),
+    Histogram(Histogram),
+}
+
+impl Metrics {
+    /// Gets the metrics of this process
+    pub fn global() -> &'static Self {
+        &GLOBAL
+    }
+
+    /// Gets or registers a counter
+    ///
+    /// # Panics
+    /// If `name` has already been registered as another type of metric
+    #[track_caller]
+    pub fn counter(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&'static str, &str)],
+    ) -> Counter {
+        match self.series(name, help, "counter", labels, || {
+            Series::Counter(Counter::default())
+        }) {
+            Series::Counter(counter) => counter,
+            _ => unreachable!("Families only contain a single type of metric"),
+        }
+    }
+
+    /// Gets or registers a gauge
+    ///
+    /// # Panics
+    /// - If `name` has already been registered as another type of metric
+    /// - If `name` and `labels` have already been registered using [`gauge_fn`](Self::gauge_fn)
+    #[track_caller]
+    pub fn gauge(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&'static str, &str)],
+    ) -> Gauge {
+        match self.series(name, help, "gauge", labels, || {
+            Series::Gauge(Gauge::default())
+        }) {
+            Series::Gauge(gauge) => gauge,
+            _ => mismatch(name, "gauge function"),
+        }
+    }
+
+    /// Registers a gauge whose value is computed by `value` whenever the metrics are rendered
+    ///
+    /// A gauge which has been registered before under the same name and labels is replaced.
+    ///
+    /// # Panics
+    /// If `name` has already been registered as another type of metric
+    #[track_caller]
+    pub fn gauge_fn(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&'static str, &str)],
+        value: impl Fn() -> i64 + Send + Sync + 'static,
+    ) {
+        family(&mut self.lock(), name, help, "gauge")
+            .series
+            .insert(owned_labels(labels), Series::GaugeFn(Arc::new(value)));
+    }
+
+    /// Gets or registers a histogram
+    ///
+    /// The `buckets` are the histogram's upper bounds in ascending order
+    /// and are only used if the histogram is registered by this call.
+    ///
+    /// # Panics
+    /// If `name` has already been registered as another type of metric
+    #[track_caller]
+    pub fn histogram(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&'static str, &str)],
+        buckets: &'static [f64],
+    ) -> Histogram {
+        match self.series(name, help, "histogram", labels, || {
+            Series::Histogram(Histogram::new(buckets))
+        }) {
+            Series::Histogram(histogram) => histogram,
+            _ => unreachable!("Families only contain a single type of metric"),
+        }
+    }
+
+    /// Renders all metrics in Prometheus' text format
+    pub fn render(&self) -> String {
+        // Take a snapshot to avoid calling the `gauge_fn`s while holding the lock
+        let families: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|(name, family)| {
+                let series: Vec<_> = family
+                    .series
+                    .iter()
+                    .map(|(labels, series)| (labels.clone(), series.clone()))
+                    .collect();
+                (*name, family.help, family.kind, series)
+            })
+            .collect();
+
+        let mut output = String::new();
+        for (name, help, kind, series) in families {
+            let _ = writeln!(output, "# HELP {name} {}", escape_help(help));
+            let _ = writeln!(output, "# TYPE {name} {kind}");
+            for (labels, series) in series {
+                let value = match series {
+                    Series::Counter(counter) => counter.get().to_string(),
+                    Series::Gauge(gauge) => gauge.get().to_string(),
+                    Series::GaugeFn(value) => value().to_string(),
+                    Series::Histogram(histogram) => {
+                        histogram.render(&mut output, name, &labels);
+                        continue;
+                    }
+                };
+                let _ = writeln!(output, "{name}{} {value}", LabelSet(&labels, None));
+            }
+        }
+        output
+    }
+
+    /// Gets the metric identified by `name` and `labels` and registers it using `create` if it is missing
+    #[track_caller]
+    fn series(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        kind: &'static str,
+        labels: &[(&'static str, &str)],
+        create: impl FnOnce() -> Series,
+    ) -> Series {
+        family(&mut self.lock(), name, help, kind)
+            .series
+            .entry(owned_labels(labels))
+            .or_insert_with(create)
+            .clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<&'static str, Family>> {
+        self.families.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A value which only ever increases
+#[derive(Debug, Clone, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    /// Increments the counter by one
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    /// Increments the counter by `value`
+    pub fn inc_by(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Gets the counter's current value
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A value which can go up and down
+#[derive(Debug, Clone, Default)]
+pub struct Gauge(Arc<AtomicI64>);
+
+impl Gauge {
+    /// Sets the gauge to `value`
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    /// Increments the gauge by one
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Decrements the gauge by one
+    pub fn dec(&self) {
+        self.add(-1);
+    }
+
+    /// Adds `value` to the gauge
+    pub fn add(&self, value: i64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Gets the gauge's current value
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Counts observed values in buckets
+#[derive(Debug, Clone)]
+pub struct Histogram(Arc<HistogramInner>);
+
+#[derive(Debug)]
+struct HistogramInner {
+    /// The buckets' upper bounds
+    bounds: &'static [f64],
+
+    /// The number of observations per bucket (not cumulative)
+    ///
+    /// The last entry counts the observations above every bound.
+    buckets: Vec<AtomicU64>,
+
+    /// The sum of all observations stored as the bits of an `f64`
+    sum: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self(Arc::new(HistogramInner {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0f64.to_bits()),
+        }))
+    }
+
+    /// Records an observation
+    pub fn observe(&self, value: f64) {
+        let bucket = self
+            .0
+            .bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.0.bounds.len());
+        self.0.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .0
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + value).to_bits())
+            });
+    }
+
+    /// Writes the histogram's `_bucket`, `_sum` and `_count` lines
+    fn render(&self, output: &mut String, name: &str, labels: &Labels) {
+        let mut count = 0;
+        for (index, bucket) in self.0.buckets.iter().enumerate() {
+            count += bucket.load(Ordering::Relaxed);
+            let bound = self
+                .0
+                .bounds
+                .get(index)
+                .map_or_else(|| "+Inf".to_string(), ToString::to_string);
+            let _ = writeln!(
+                output,
+                "{name}_bucket{} {count}",
+                LabelSet(labels, Some(&bound))
+            );
+        }
+        let sum = f64::from_bits(self.0.sum.load(Ordering::Relaxed));
+        let _ = writeln!(output, "{name}_sum{} {sum}", LabelSet(labels, None));
+        let _ = writeln!(output, "{name}_count{} {count}", LabelSet(labels, None));
+    }
+}
+
+/// Formats labels as `{name="value",...}` including an optional `le` label
+struct LabelSet<'a>(&'a Labels, Option<&'a str>);
+
+impl fmt::Display for LabelSet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let le = self.1.map(|le| ("le", le));
+        let mut labels = self
+            .0
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .chain(le)
+            .peekable();
+        if labels.peek().is_none() {
+            return Ok(());
+        }
+
+        f.write_str("{")?;
+        for (index, (name, value)) in labels.enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{name}=\"")?;
+            for char in value.chars() {
+                match char {
+                    '\\' => f.write_str("\\\\")?,
+                    '"' => f.write_str("\\\"")?,
+                    '\n' => f.write_str("\\n")?,
+                    char => f.write_char(char)?,
+                }
+            }
+            f.write_str("\"")?;
+        }
+        f.write_str("}")
+    }
+}
+
+fn owned_labels(labels: &[(&'static str, &str)]) -> Labels {
+    labels
+        .iter()
+        .map(|(name, value)| (*name, value.to_string()))
+        .collect()
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Gets the family of metrics named `name` and registers it if it is missing
+#[track_caller]
+fn family<'a>(
+    families: &'a mut BTreeMap<&'static str, Family>,
+    name: &'static str,
+    help: &'static str,
+    kind: &'static str,
+) -> &'a mut Family {
+    let family = families.entry(name).or_insert_with(|| Family {
+        help,
+        kind,
+        series: BTreeMap::new(),
+    });
+    if family.kind != kind {
+        mismatch(name, family.kind);
+    }
+    family
+}
+
+#[track_caller]
+fn mismatch(name: &str, kind: &str) -> ! {
+    panic!("The metric '{name}' has already been registered as {kind}");
+}
@@ -1,12 +1,19 @@
+use std::fmt;
+
 use rorm::Database;
 use rorm::DatabaseConfiguration;
 use rorm::DatabaseDriver;
+use rorm::db::Executor;
+use rorm::db::executor::Nothing;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::DependencyRefs;
 use crate::InitError;
 use crate::Module;
 use crate::PreInitError;
+use crate::metrics::Metrics;
+use crate::module::registry::HealthCheck;
 
 /// Config struct the [`DatabaseSetup::Default`] will deserialize from environment variables
 #[derive(Serialize, Deserialize, Debug)]
@@ -19,13 +26,27 @@ pub struct DatabaseConfig {
 }
 
 /// Enum declaring how the database should be configured
-#[derive(Default, Debug)]
+#[derive(Default)]
 pub enum DatabaseSetup {
     #[default]
     Default,
     Custom(DatabaseConfiguration),
 }
 
+impl fmt::Debug for DatabaseSetup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseSetup::Default => f.write_str("Default"),
+            // The driver contains the database's password
+            DatabaseSetup::Custom(config) => f
+                .debug_struct("Custom")
+                .field("min_connections", &config.min_connections)
+                .field("max_connections", &config.max_connections)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
 impl Module for Database {
     type Setup = DatabaseSetup;
 
@@ -58,8 +79,29 @@ impl Module for Database {
 
     async fn init(
         config: Self::PreInit,
-        _dependencies: &mut Self::Dependencies,
+        _dependencies: DependencyRefs<Self>,
     ) -> Result<Self, InitError> {
         Ok(Database::connect(config).await?)
     }
+
+    async fn health_check(&'static self) -> HealthCheck {
+        let up = Metrics::global().gauge(
+            "rlune_db_up",
+            "Whether the last health check reached the database",
+            &[],
+        );
+        match self
+            .execute::<Nothing>("SELECT 1;".to_string(), Vec::new())
+            .await
+        {
+            Ok(()) => {
+                up.set(1);
+                HealthCheck::healthy()
+            }
+            Err(error) => {
+                up.set(0);
+                HealthCheck::unhealthy(error.to_string())
+            }
+        }
+    }
 }
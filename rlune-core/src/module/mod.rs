@@ -10,6 +10,7 @@ use std::future::Future;
 use thiserror::Error;
 
 pub use crate::module::impls::database::DatabaseSetup;
+use crate::module::registry::HealthCheck;
 use crate::module::registry::ModuleDependencies;
 use crate::module::registry::Registry;
 
@@ -37,12 +38,14 @@ pub mod registry;
 ///
 /// ## init
 ///
-/// Every modules' `init` function is run sequentially in the order defined by the application author.
+/// Every modules' `init` function is run after the `init` of its [dependencies](Module::Dependencies).
 ///
 /// This step should perform the module's main initialization
 /// and returns the module singleton which is stored in globally.
 ///
-/// TODO: dependencies
+/// Modules which don't depend on each other are initialized concurrently.
+/// Dependencies which haven't been registered by the application author are registered
+/// automatically using their default [`Setup`](Module::Setup).
 ///
 /// ## post init
 ///
@@ -51,6 +54,53 @@ pub mod registry;
 /// At this point every module has been initialized and is available globally.
 ///
 /// Modules which others depend on have been made aware of their dependents and may run some finishing initialization code.
+///
+/// # Shutdown
+///
+/// Once the application stops (i.e. the webserver has drained its connections),
+/// every modules' `shutdown` function is run sequentially in reverse order of their `init`.
+/// So a module is shut down before the modules it depends on.
+///
+/// Each module has a limited amount of time to flush buffers, close connections or finish background work
+/// (see [`RegistryBuilder::shutdown_timeout`](crate::module::registry::builder::RegistryBuilder::shutdown_timeout)).
+/// Errors don't prevent the remaining modules from shutting down.
+///
+/// # Reload
+///
+/// While the application is running, a reload may be requested
+/// (i.e. by sending `SIGHUP` or using an admin endpoint).
+/// Every modules' `reload` function is then run sequentially in order of their `init`.
+/// So a module is reloaded after the modules it depends on.
+///
+/// A module should re-read its configuration and replace the state derived from it in one step.
+/// Storing such state in a [`SwapLock`](crate::stuff::swap_lock::SwapLock) (behind an `Arc` if it is expensive to clone)
+/// ensures that code using the module always sees a consistent snapshot:
+///
+/// ```ignore
+/// struct RateLimit {
+///     config: SwapLock<Arc<RateLimitConfig>>,
+/// }
+///
+/// impl Module for RateLimit {
+///     // ...
+///
+///     async fn reload(&'static self) -> Result<(), ReloadError> {
+///         let config = RateLimitConfig::from_env()?;
+///         self.config.swap(Arc::new(config));
+///         Ok(())
+///     }
+/// }
+/// ```
+///
+/// If reloading fails, the module should keep its previous state.
+/// Errors don't prevent the remaining modules from reloading.
+///
+/// # Health checks
+///
+/// Every modules' `health_check` function is run concurrently whenever the application's health is queried
+/// (i.e. by a load balancer or Kubernetes' probes).
+/// It should be cheap and report whether the module is able to do its job,
+/// for example whether an external service can be reached.
 pub trait Module: Sized + Send + Sync + 'static {
     /// A type which is constructed by an application author to declare how this module should configure itself.Add commentMore actions
     ///
@@ -60,6 +110,9 @@ pub trait Module: Sized + Send + Sync + 'static {
     /// Instead, it should declare how the sysadmin is able to configure the module.
     ///
     /// If your module doesn't need to support different setups then `()` would be a good default.
+    ///
+    /// Its `Debug` output is served by the module introspection endpoint,
+    /// so it must not contain secrets like passwords or keys.
     type Setup: fmt::Debug + Default + Sized + Send + Sync + 'static;
 
     /// Arbitrary data passed from the `pre_init` step to `init`
@@ -80,14 +133,22 @@ pub trait Module: Sized + Send + Sync + 'static {
     ) -> impl Future<Output = Result<Self::PreInit, PreInitError>> + Send;
 
     /// A tuple of [`Module`]s which need to be initialized before this one.
+    ///
+    /// References to them are passed to [`Module::init`] and they are registered automatically if necessary.
+    ///
+    /// Besides modules, the tuple may contain `Option<M>` for modules which are only used if registered
+    /// and [`Dyn<I>`](crate::module::registry::Dyn) for whatever module provides the interface `I`
+    /// (see [`ModuleDependency`](crate::module::registry::ModuleDependency)).
     type Dependencies: ModuleDependencies;
 
     /// The main initialization of the module
     ///
+    /// It receives `&'static` references to its initialized [dependencies](Module::Dependencies).
+    ///
     /// (see [Module Init](Module#init))
     fn init(
         pre_init: Self::PreInit,
-        dependencies: &mut Self::Dependencies,
+        dependencies: DependencyRefs<Self>,
     ) -> impl Future<Output = Result<Self, InitError>> + Send;
 
     /// Post initialization run concurrently with all other modules'
@@ -97,11 +158,39 @@ pub trait Module: Sized + Send + Sync + 'static {
         async { Ok(()) }
     }
 
+    /// Shutdown run sequentially in reverse initialization order
+    ///
+    /// (see [Module Shutdown](Module#shutdown))
+    fn shutdown(&'static self) -> impl Future<Output = Result<(), ShutdownError>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Reload run sequentially in initialization order
+    ///
+    /// (see [Module Reload](Module#reload))
+    ///
+    /// If your module doesn't have any configuration which could change at runtime,
+    /// the default implementation doing nothing is fine.
+    fn reload(&'static self) -> impl Future<Output = Result<(), ReloadError>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Checks whether the module is able to do its job
+    ///
+    /// (see [Module Health checks](Module#health-checks))
+    ///
+    /// The check is aborted if it takes too long, so it doesn't need to implement a timeout itself.
+    fn health_check(&'static self) -> impl Future<Output = HealthCheck> + Send {
+        async { HealthCheck::healthy() }
+    }
+
     /// Gets the module's global instance
     ///
     /// This method should be used after every modules' `init` ran.
     /// I.e. in a module's `post_init` or the applications operation after that.
     ///
+    /// Inside a [`Registry::scope`] the instance is taken from the scoped registry instead.
+    ///
     /// # Panics
     /// If the module has not been initialized yet.
     fn global() -> &'static Self {
@@ -122,9 +211,16 @@ pub trait Module: Sized + Send + Sync + 'static {
     }
 }
 
+/// The references to a module's initialized [dependencies](Module::Dependencies) passed to [`Module::init`]
+///
+/// For `type Dependencies = (Database, Option<Mailer>)` this is `(&'static Database, Option<&'static Mailer>)`.
+pub type DependencyRefs<M> = <<M as Module>::Dependencies as ModuleDependencies>::Refs;
+
 pub type PreInitError = Box<dyn Error + Send + Sync + 'static>;
 pub type InitError = Box<dyn Error + Send + Sync + 'static>;
 pub type PostInitError = Box<dyn Error + Send + Sync + 'static>;
+pub type ShutdownError = Box<dyn Error + Send + Sync + 'static>;
+pub type ReloadError = Box<dyn Error + Send + Sync + 'static>;
 
 /// Error returned by [`Module::try_global`]
 #[derive(Error, Debug)]
@@ -145,4 +241,13 @@ pub enum TryGlobalError {
         /// [`type_name`] of the requested `Module`
         module_type: &'static str,
     },
+
+    /// No `Module` has been registered as provider of the requested interface
+    ///
+    /// (see [`ProvidesInterface`](crate::module::registry::ProvidesInterface))
+    #[error("no module providing the interface '{interface_type}' has been registered")]
+    Interface {
+        /// [`type_name`] of the requested interface
+        interface_type: &'static str,
+    },
 }
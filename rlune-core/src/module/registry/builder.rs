@@ -1,7 +1,15 @@
 use std::any::TypeId;
 use std::any::type_name;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use std::time::Instant;
 
 use futures_concurrency::future::Join;
 use futures_lite::future;
@@ -16,13 +24,52 @@ use tracing::trace_span;
 use crate::module;
 use crate::module::Module;
 use crate::module::registry::DynModule;
+use crate::module::registry::HealthCheck;
 use crate::module::registry::ModuleDependencies;
 use crate::module::registry::Registry;
-use crate::module::registry::module_set::OwnedModulesSet;
+use crate::module::registry::info::ModuleInfo;
+use crate::module::registry::info::PhaseTimings;
+use crate::module::registry::interface::Interface;
+use crate::module::registry::interface::InterfaceMap;
+use crate::module::registry::interface::ProvidesInterface;
+use crate::module::registry::module_set::LeakedModuleSet;
+use crate::module::registry::scope::propagate_scope;
+use crate::module::registry::tasks::Tasks;
 
-#[derive(Default)]
 pub struct RegistryBuilder {
-    modules: Vec<(TypeId, UninitModule)>,
+    modules: Vec<Registration>,
+    interfaces: InterfaceMap,
+    shutdown_timeout: Duration,
+}
+
+/// A module registered in the [`RegistryBuilder`]
+struct Registration {
+    type_id: TypeId,
+    name: &'static str,
+
+    /// The `TypeId`, name and optionality of every dependency
+    ///
+    /// The `TypeId` might refer to an interface instead of a module.
+    dependencies: Vec<(TypeId, &'static str, bool)>,
+
+    /// Whether the module has been registered by the application author
+    /// instead of automatically as someone's dependency
+    explicit: bool,
+
+    /// The `Debug` output of the module's setup
+    setup: Option<String>,
+
+    module: UninitModule,
+}
+
+impl Default for RegistryBuilder {
+    fn default() -> Self {
+        Self {
+            modules: Vec::new(),
+            interfaces: InterfaceMap::new(),
+            shutdown_timeout: Duration::from_secs(10),
+        }
+    }
 }
 
 impl RegistryBuilder {
@@ -31,117 +78,131 @@ impl RegistryBuilder {
         Self::default()
     }
 
-    fn contains_module(&self, type_id: TypeId) -> bool {
-        self.modules.iter().any(|(id, _)| *id == type_id)
+    /// Sets the time each module is granted to shut down
+    ///
+    /// A module which doesn't finish its [`Module::shutdown`] in time is cancelled
+    /// and reported as error. Defaults to 10 seconds.
+    pub fn shutdown_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    fn position(&self, type_id: TypeId) -> Option<usize> {
+        self.modules
+            .iter()
+            .position(|registration| registration.type_id == type_id)
     }
 
     /// Adds a new module to the `RegistryBuilder`
     ///
-    /// Calling this method twice with the same `T` is not an error but will only add it once.
+    /// The module's dependencies which haven't been registered yet are registered automatically
+    /// using their default setup. Registering such a dependency explicitly later on
+    /// replaces the automatic registration.
+    ///
+    /// # Panics
+    /// If the same `T` is registered explicitly twice.
     #[instrument(level = "trace", name = "RegistryBuilder::register_module", skip(self), fields(module.name = type_name::<T>()))]
     pub fn register_module<T: Module>(&mut self, setup: T::Setup) -> &mut Self {
-        if self.contains_module(TypeId::of::<T>()) {
+        if self.register_explicit(Registration::new::<T>(setup, true)) {
+            <T::Dependencies as ModuleDependencies>::register_defaults(self);
+        }
+        self
+    }
+
+    /// Adds an already initialized module to the `RegistryBuilder`
+    ///
+    /// The module's `pre_init` and `init` are skipped while `post_init` and `shutdown` still run.
+    /// This is intended for tests which want to replace a module with one constructed by the test
+    /// (for example using a test database) without running the module's actual initialization.
+    ///
+    /// Registering an instance replaces an automatic registration like [`register_module`](Self::register_module) does.
+    ///
+    /// # Panics
+    /// If the same `T` is registered explicitly twice.
+    #[instrument(level = "trace", name = "RegistryBuilder::register_instance", skip_all, fields(module.name = type_name::<T>()))]
+    pub fn register_instance<T: Module>(&mut self, instance: T) -> &mut Self {
+        self.register_explicit(Registration::instance::<T>(instance));
+        self
+    }
+
+    /// Adds or replaces an explicit registration
+    ///
+    /// Returns whether the module hasn't been registered before.
+    fn register_explicit(&mut self, registration: Registration) -> bool {
+        match self.position(registration.type_id) {
+            Some(index) if self.modules[index].explicit => {
+                panic!(
+                    "The module '{}' is being registered twice",
+                    registration.name
+                );
+            }
+            Some(index) => {
+                debug!(
+                    module.name = registration.name,
+                    "Replaced automatically registered module"
+                );
+                self.modules[index] = registration;
+                false
+            }
+            None => {
+                debug!(module.name = registration.name, "Registered module");
+                self.modules.push(registration);
+                true
+            }
+        }
+    }
+
+    /// Registers the module `M` as provider of the interface `I`
+    ///
+    /// Other modules can depend on it using [`Dyn<I>`](crate::module::registry::Dyn)
+    /// and it can be accessed through [`Dyn::global`](crate::module::registry::Dyn::global).
+    /// `M` itself is registered automatically using its default setup if necessary.
+    ///
+    /// # Panics
+    /// If another module has already been registered as provider of `I`.
+    pub fn register_interface<I: ?Sized + 'static, M: ProvidesInterface<I>>(
+        &mut self,
+    ) -> &mut Self {
+        if self
+            .interfaces
+            .insert(TypeId::of::<I>(), Interface::new::<I, M>())
+            .is_some()
+        {
             panic!(
-                "The module '{}' is being registered twice",
-                type_name::<T>()
+                "The interface '{}' is being provided twice",
+                type_name::<I>()
             );
-        } else {
-            <T::Dependencies as ModuleDependencies>::for_each(|dependency_id, dependency_name| {
-                if !self.contains_module(dependency_id) {
-                    panic!(
-                        "Module '{}' depends on '{dependency_name}'. Please register it first.",
-                        type_name::<T>(),
-                    );
-                }
-            });
-
-            self.modules.push((
-                TypeId::of::<T>(),
-                BoxDynFnOnce::new(move |()| {
-                    tokio::spawn(async {
-                        let pre_init = async move {
-                            let result = T::pre_init(setup).await;
-                            match &result {
-                                Ok(_) => trace!("Finished pre init"),
-                                Err(_) => trace!("Failed pre init"),
-                            }
-                            result
-                        }
-                        .instrument(trace_span!(
-                            "Module::pre_init",
-                            module.name = type_name::<T>()
-                        ))
-                        .await?;
-
-                        Ok(BoxDynFnOnce::new(move |mut modules: OwnedModulesSet| {
-                            Box::pin(async move {
-                                let mut dependencies =
-                                    <T::Dependencies as ModuleDependencies>::take(&mut modules);
-
-                                let t = {
-                                    let dependencies = &mut dependencies;
-                                    async move {
-                                        let result = T::init(pre_init, dependencies).await;
-                                        match &result {
-                                            Ok(_) => trace!("Finished init"),
-                                            Err(_) => trace!("Failed init"),
-                                        }
-                                        result
-                                    }
-                                    .instrument(trace_span!(
-                                        "Module::init",
-                                        module.name = type_name::<T>()
-                                    ))
-                                    .await?
-                                };
-
-                                <T::Dependencies as ModuleDependencies>::put_back(
-                                    dependencies,
-                                    &mut modules,
-                                );
-                                modules.insert(t);
-                                Ok(modules)
-                            }) as future::Boxed<_>
-                        }))
-                    })
-                }) as UninitModule,
-            ));
-            debug!(module.name = type_name::<T>(), "Registered module");
         }
+        self.register_default::<M>();
         self
     }
 
+    /// Registers a module with its default setup unless it has already been registered
+    pub(crate) fn register_default<T: Module>(&mut self) {
+        if self.position(TypeId::of::<T>()).is_none() {
+            self.modules
+                .push(Registration::new::<T>(T::Setup::default(), false));
+            debug!(
+                module.name = type_name::<T>(),
+                "Registered module automatically"
+            );
+            <T::Dependencies as ModuleDependencies>::register_defaults(self);
+        }
+    }
+
     /// Initialized all registered modules
     ///
     /// and makes the registry available through [`Registry::global`].
+    ///
+    /// # Panics
+    /// If the global registry has already been initialized.
     #[instrument(level = "trace", name = "RegistryBuilder::init", skip(self))]
     pub async fn init(&mut self) -> Result<(), InitError> {
-        let pre_init_modules = process_join_results(
-            self.modules
-                .drain(..)
-                .map(|(_, x)| x.call(()))
-                .collect::<Vec<_>>()
-                .join()
-                .await,
-        )
-        .map_err(InitError::PreInit)?;
-
-        let mut modules = OwnedModulesSet::new();
-        for pre_init_module in pre_init_modules {
-            modules = pre_init_module
-                .call(modules)
-                .await
-                .map_err(InitError::Init)?;
-        }
+        let registry = self.init_modules().await?;
 
         let registry = {
             let global = Registry::raw_global();
-            if global
-                .set(Registry {
-                    modules: modules.leak(),
-                })
-                .is_err()
-            {
+            if global.set(registry).is_err() {
                 panic!("The module registry has already been initialized once");
             }
             global
@@ -149,43 +210,390 @@ impl RegistryBuilder {
                 .unwrap_or_else(|| unreachable!("The OnceLock has just been set"))
         };
 
-        process_join_results(
-            registry
-                .modules
-                .iter()
-                .map(|init_module| init_module.post_init())
-                .collect::<Vec<_>>()
-                .join()
-                .await,
-        )
-        .map_err(InitError::PostInit)?;
+        registry.post_init().await
+    }
+
+    /// Initializes all registered modules into a new registry without making it global
+    ///
+    /// Unlike [`RegistryBuilder::init`], this method can be called any number of times per process.
+    /// The returned registry is leaked and should be entered using [`Registry::scope`].
+    /// Its modules' `post_init` already runs inside that scope.
+    ///
+    /// This is intended for tests which require isolated registries with different setups.
+    #[instrument(level = "trace", name = "RegistryBuilder::build", skip(self))]
+    pub async fn build(&mut self) -> Result<&'static Registry, InitError> {
+        let registry: &'static Registry = Box::leak(Box::new(self.init_modules().await?));
+        registry.scope(registry.post_init()).await?;
+        Ok(registry)
+    }
+
+    /// Runs every module's `pre_init` and `init`
+    async fn init_modules(&mut self) -> Result<Registry, InitError> {
+        let mut registrations = mem::take(&mut self.modules);
+        let interfaces = Arc::new(mem::take(&mut self.interfaces));
+        let dependencies = resolve_dependencies(&registrations, &interfaces)?;
+        let batches = sort_into_batches(&registrations, &dependencies)?;
+
+        let mut infos: Vec<_> = registrations
+            .iter()
+            .zip(&dependencies)
+            .map(|(registration, dependencies)| ModuleInfo {
+                name: registration.name,
+                dependencies: dependencies
+                    .iter()
+                    .filter_map(|dependency| {
+                        registrations
+                            .iter()
+                            .find(|registration| registration.type_id == *dependency)
+                            .map(|registration| registration.name)
+                    })
+                    .collect(),
+                setup: registration.setup.clone(),
+                automatic: !registration.explicit,
+                timings: PhaseTimings::default(),
+            })
+            .collect();
+
+        let mut metas = Vec::with_capacity(registrations.len());
+        let mut uninit_modules = Vec::with_capacity(registrations.len());
+        for (registration, dependencies) in registrations.drain(..).zip(dependencies) {
+            metas.push((registration.type_id, dependencies));
+            let start = Instant::now();
+            let handle = registration.module.call(());
+            uninit_modules.push(async move {
+                let result = handle.await;
+                (result, start.elapsed())
+            });
+        }
+
+        let mut join_results = Vec::with_capacity(uninit_modules.len());
+        for (index, (result, elapsed)) in uninit_modules.join().await.into_iter().enumerate() {
+            infos[index].timings.pre_init = Some(elapsed);
+            join_results.push(result);
+        }
+        let mut pre_init_modules: Vec<_> = process_join_results(join_results)
+            .map_err(InitError::PreInit)?
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        // Modules are leaked as soon as they are initialized,
+        // so every module depending on them can receive a `&'static` reference.
+        let mut modules = LeakedModuleSet::with_interfaces(interfaces.clone());
+        let mut init_order = Vec::with_capacity(metas.len());
+        let mut init_indexes = Vec::with_capacity(metas.len());
+        for batch in batches {
+            let mut futures = Vec::with_capacity(batch.len());
+            for index in batch {
+                let (type_id, dependencies) = &metas[index];
+
+                // Each module receives its own set containing only its dependencies
+                let mut subset = LeakedModuleSet::with_interfaces(interfaces.clone());
+                for dependency in dependencies {
+                    if let Some(module) = modules.get_dyn(*dependency) {
+                        subset.insert_dyn(*dependency, module);
+                    }
+                }
+                let pre_init_module = pre_init_modules[index]
+                    .take()
+                    .unwrap_or_else(|| unreachable!("Every module appears in exactly one batch"));
+
+                init_order.push(*type_id);
+                init_indexes.push(index);
+                let init = pre_init_module.call(subset);
+                futures.push(async move {
+                    let start = Instant::now();
+                    let result = init.await;
+                    (index, result, start.elapsed())
+                });
+            }
+
+            let mut errors = Vec::new();
+            for (index, result, elapsed) in futures.join().await {
+                infos[index].timings.init = Some(elapsed);
+                match result {
+                    Ok(module) => modules.insert_dyn(metas[index].0, Box::leak(module)),
+                    Err(error) => errors.push(error),
+                }
+            }
+            if !errors.is_empty() {
+                return Err(InitError::Init(errors));
+            }
+        }
+
+        // Order the infos like the modules have been initialized
+        let mut infos: Vec<_> = infos.into_iter().map(Some).collect();
+        let infos = init_indexes
+            .into_iter()
+            .filter_map(|index| infos[index].take())
+            .collect();
+
+        Ok(Registry {
+            modules,
+            init_order,
+            shutdown_timeout: self.shutdown_timeout,
+            interfaces,
+            infos: Mutex::new(infos),
+            shutting_down: AtomicBool::new(false),
+            tasks: Tasks::new(),
+        })
+    }
+}
+
+impl Registry {
+    /// Runs every module's `post_init` concurrently
+    async fn post_init(&'static self) -> Result<(), InitError> {
+        let mut join_results = Vec::with_capacity(self.init_order.len());
+        let mut timings = Vec::with_capacity(self.init_order.len());
+        let post_inits = self
+            .init_order
+            .iter()
+            .filter_map(|type_id| self.modules.get_dyn(*type_id))
+            .map(|init_module| {
+                let start = Instant::now();
+                let handle = init_module.post_init();
+                async move {
+                    let result = handle.await;
+                    (result, start.elapsed())
+                }
+            })
+            .collect::<Vec<_>>();
+        for (result, elapsed) in post_inits.join().await {
+            join_results.push(result);
+            timings.push(elapsed);
+        }
+
+        {
+            let mut infos = self.infos.lock().unwrap_or_else(PoisonError::into_inner);
+            for (info, elapsed) in infos.iter_mut().zip(timings) {
+                info.timings.post_init = Some(elapsed);
+            }
+        }
+
+        process_join_results(join_results).map_err(InitError::PostInit)?;
 
         Ok(())
     }
 }
 
+impl Registration {
+    /// Constructs a registration for an already initialized module
+    fn instance<T: Module>(instance: T) -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+            dependencies: Vec::new(),
+            explicit: true,
+            setup: None,
+            module: BoxDynFnOnce::new(move |()| {
+                tokio::spawn(async move {
+                    Ok(BoxDynFnOnce::new(move |_: LeakedModuleSet| {
+                        Box::pin(async move { Ok(Box::new(instance) as Box<dyn DynModule>) })
+                            as future::Boxed<_>
+                    }) as PreInitModule)
+                })
+            }) as UninitModule,
+        }
+    }
+
+    fn new<T: Module>(setup: T::Setup, explicit: bool) -> Self {
+        let mut dependencies = Vec::new();
+        <T::Dependencies as ModuleDependencies>::for_each(|type_id, name, optional| {
+            dependencies.push((type_id, name, optional));
+        });
+
+        Self {
+            type_id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+            dependencies,
+            explicit,
+            setup: Some(format!("{setup:?}")),
+            module: BoxDynFnOnce::new(move |()| {
+                tokio::spawn(async {
+                    let pre_init = async move {
+                        let result = T::pre_init(setup).await;
+                        match &result {
+                            Ok(_) => trace!("Finished pre init"),
+                            Err(_) => trace!("Failed pre init"),
+                        }
+                        result
+                    }
+                    .instrument(trace_span!(
+                        "Module::pre_init",
+                        module.name = type_name::<T>()
+                    ))
+                    .await?;
+
+                    Ok(BoxDynFnOnce::new(move |modules: LeakedModuleSet| {
+                        let dependencies = <T::Dependencies as ModuleDependencies>::get(&modules);
+                        Box::pin(
+                            async move {
+                                let result = T::init(pre_init, dependencies).await;
+                                match &result {
+                                    Ok(_) => trace!("Finished init"),
+                                    Err(_) => trace!("Failed init"),
+                                }
+                                Ok(Box::new(result?) as Box<dyn DynModule>)
+                            }
+                            .instrument(trace_span!(
+                                "Module::init",
+                                module.name = type_name::<T>()
+                            )),
+                        ) as future::Boxed<_>
+                    }) as PreInitModule)
+                })
+            }) as UninitModule,
+        }
+    }
+}
+
+/// Resolves the registered modules' dependencies to the `TypeId`s of registered modules
+///
+/// Interfaces are replaced with their providing modules
+/// and optional dependencies which haven't been registered are dropped.
+fn resolve_dependencies(
+    registrations: &[Registration],
+    interfaces: &InterfaceMap,
+) -> Result<Vec<Vec<TypeId>>, InitError> {
+    let is_registered = |type_id: TypeId| {
+        registrations
+            .iter()
+            .any(|registration| registration.type_id == type_id)
+    };
+
+    let mut resolved = Vec::with_capacity(registrations.len());
+    for registration in registrations {
+        let mut dependencies = Vec::with_capacity(registration.dependencies.len());
+        for &(type_id, name, optional) in &registration.dependencies {
+            let type_id = interfaces
+                .get(&type_id)
+                .map_or(type_id, |interface| interface.module);
+            if is_registered(type_id) {
+                dependencies.push(type_id);
+            } else if !optional {
+                return Err(InitError::MissingDependency {
+                    module: registration.name,
+                    dependency: name,
+                });
+            }
+        }
+        resolved.push(dependencies);
+    }
+    Ok(resolved)
+}
+
+/// Sorts the registered modules topologically into batches of indexes
+///
+/// The batches have to be initialized one after another.
+/// The modules within a batch don't depend on each other, so they can be initialized concurrently.
+fn sort_into_batches(
+    registrations: &[Registration],
+    dependencies: &[Vec<TypeId>],
+) -> Result<Vec<Vec<usize>>, InitError> {
+    let mut done = HashSet::new();
+    let mut remaining: Vec<usize> = (0..registrations.len()).collect();
+    let mut batches = Vec::new();
+
+    while !remaining.is_empty() {
+        let (ready, not_ready): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|&index| {
+            dependencies[index]
+                .iter()
+                .all(|dependency| done.contains(dependency))
+        });
+
+        if ready.is_empty() {
+            return Err(InitError::DependencyCycle(find_cycle(
+                registrations,
+                dependencies,
+                &not_ready,
+            )));
+        }
+
+        done.extend(ready.iter().map(|&index| registrations[index].type_id));
+        batches.push(ready);
+
+        remaining = not_ready;
+    }
+
+    Ok(batches)
+}
+
+/// Finds a dependency cycle among modules which can't be initialized
+///
+/// Returns the names of the modules forming the cycle
+/// starting and ending with the same module.
+fn find_cycle(
+    registrations: &[Registration],
+    dependencies: &[Vec<TypeId>],
+    stuck: &[usize],
+) -> Vec<&'static str> {
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = stuck[0];
+    loop {
+        if let Some(start) = path.iter().position(|&index| index == current) {
+            let mut cycle: Vec<_> = path[start..]
+                .iter()
+                .map(|&index| registrations[index].name)
+                .collect();
+            cycle.push(registrations[current].name);
+            return cycle;
+        }
+        path.push(current);
+
+        // Every stuck module depends on at least one other stuck module
+        current = dependencies[current]
+            .iter()
+            .find_map(|dependency| {
+                stuck
+                    .iter()
+                    .copied()
+                    .find(|&index| registrations[index].type_id == *dependency)
+            })
+            .unwrap_or_else(|| unreachable!("A stuck module has a stuck dependency"));
+    }
+}
+
 #[derive(Debug)]
 pub enum InitError {
+    /// The registered modules depend on each other in a cycle
+    ///
+    /// Contains the names of the modules forming the cycle.
+    DependencyCycle(Vec<&'static str>),
+    /// A module depends on an interface no module has been registered for
+    MissingDependency {
+        module: &'static str,
+        dependency: &'static str,
+    },
     PreInit(Vec<module::PreInitError>),
-    Init(module::InitError),
+    Init(Vec<module::InitError>),
     PostInit(Vec<module::PostInitError>),
 }
 
 impl fmt::Display for InitError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let phase = match self {
-            InitError::PreInit(_) => "pre-",
-            InitError::Init(_) => "",
-            InitError::PostInit(_) => "post-",
+        let (phase, errors) = match self {
+            InitError::DependencyCycle(modules) => {
+                return write!(
+                    f,
+                    "Modules depend on each other in a cycle: {}",
+                    modules.join(" -> ")
+                );
+            }
+            InitError::MissingDependency { module, dependency } => {
+                return write!(
+                    f,
+                    "Module '{module}' depends on '{dependency}' which is provided by no module"
+                );
+            }
+            InitError::PreInit(errors) => ("pre-", errors),
+            InitError::Init(errors) => ("", errors),
+            InitError::PostInit(errors) => ("post-", errors),
         };
-        let (first, rest) = match self {
-            InitError::PreInit(errors) => errors.split_first(),
-            InitError::Init(error) => Some((error, [].as_slice())),
-            InitError::PostInit(errors) => errors.split_first(),
-        }
-        .unwrap_or_else(|| unreachable!("Error lists should not be empty"));
+        let (first, rest) = errors
+            .split_first()
+            .unwrap_or_else(|| unreachable!("Error lists should not be empty"));
         write!(f, "Error during module {phase}initialisation: {first}")?;
-        if rest.is_empty() {
+        if !rest.is_empty() {
             write!(f, " (and {} more...)", rest.len())?;
         }
         Ok(())
@@ -196,13 +604,17 @@ impl Error for InitError {}
 /// An uninitialised module waiting to be pre-initialised
 type UninitModule = BoxDynFnOnce<(), JoinHandle<Result<PreInitModule, module::PreInitError>>>;
 
-/// A pre-initialised modules waiting to be initialised
+/// A pre-initialised modules waiting to be initialised with its dependencies
 type PreInitModule =
-    BoxDynFnOnce<OwnedModulesSet, future::Boxed<Result<OwnedModulesSet, module::InitError>>>;
+    BoxDynFnOnce<LeakedModuleSet, future::Boxed<Result<Box<dyn DynModule>, module::InitError>>>;
 
 impl<M: Module> DynModule for M {
+    fn name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
     fn post_init(&'static self) -> JoinHandle<Result<(), module::PostInitError>> {
-        tokio::spawn(
+        tokio::spawn(propagate_scope(
             async move {
                 let result = Module::post_init(self).await;
                 match &result {
@@ -215,7 +627,47 @@ impl<M: Module> DynModule for M {
                 "Module::post_init",
                 module.name = type_name::<Self>()
             )),
-        )
+        ))
+    }
+
+    fn shutdown(&'static self) -> JoinHandle<Result<(), module::ShutdownError>> {
+        tokio::spawn(propagate_scope(
+            async move {
+                let result = Module::shutdown(self).await;
+                match &result {
+                    Ok(_) => trace!("Finished shutdown"),
+                    Err(_) => trace!("Failed shutdown"),
+                }
+                result
+            }
+            .instrument(trace_span!(
+                "Module::shutdown",
+                module.name = type_name::<Self>()
+            )),
+        ))
+    }
+
+    fn reload(&'static self) -> JoinHandle<Result<(), module::ReloadError>> {
+        tokio::spawn(propagate_scope(
+            async move {
+                let result = Module::reload(self).await;
+                match &result {
+                    Ok(_) => trace!("Finished reload"),
+                    Err(_) => trace!("Failed reload"),
+                }
+                result
+            }
+            .instrument(trace_span!(
+                "Module::reload",
+                module.name = type_name::<Self>()
+            )),
+        ))
+    }
+
+    fn health_check(&'static self) -> JoinHandle<HealthCheck> {
+        tokio::spawn(propagate_scope(Module::health_check(self).instrument(
+            trace_span!("Module::health_check", module.name = type_name::<Self>()),
+        )))
     }
 }
 
@@ -245,25 +697,7 @@ fn process_join_results<T, E: From<String>>(
     let mut ts = Vec::new();
     let mut errors = Vec::new();
     for join_result in vec {
-        let result = join_result.unwrap_or_else(|join_error| {
-            Err(E::from(
-                join_error
-                    .try_into_panic()
-                    .map(|panic| {
-                        format!(
-                            "Module panicked: {}",
-                            if let Some(string) = panic.downcast_ref::<String>() {
-                                string.as_str()
-                            } else if let Some(string) = panic.downcast_ref::<&'static str>() {
-                                string
-                            } else {
-                                "Box<dyn Any>"
-                            }
-                        )
-                    })
-                    .unwrap_or_else(|join_error| format!("Couldn't join: {join_error}")),
-            ))
-        });
+        let result = join_result.unwrap_or_else(|join_error| Err(join_error_into(join_error)));
 
         match result {
             Ok(t) => ts.push(t),
@@ -277,3 +711,216 @@ fn process_join_results<T, E: From<String>>(
         Err(errors)
     }
 }
+
+/// Converts a `JoinError` of a module's task into an error describing the failure
+pub(super) fn join_error_into<E: From<String>>(join_error: JoinError) -> E {
+    E::from(
+        join_error
+            .try_into_panic()
+            .map(|panic| {
+                format!(
+                    "Module panicked: {}",
+                    if let Some(string) = panic.downcast_ref::<String>() {
+                        string.as_str()
+                    } else if let Some(string) = panic.downcast_ref::<&'static str>() {
+                        string
+                    } else {
+                        "Box<dyn Any>"
+                    }
+                )
+            })
+            .unwrap_or_else(|join_error| format!("Couldn't join: {join_error}")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DependencyRefs;
+    use crate::module::registry::Dyn;
+
+    macro_rules! dummy_module {
+        ($name:ident, $dependencies:ty) => {
+            struct $name;
+
+            impl Module for $name {
+                type Setup = ();
+                type PreInit = ();
+
+                async fn pre_init((): Self::Setup) -> Result<Self::PreInit, module::PreInitError> {
+                    Ok(())
+                }
+
+                type Dependencies = $dependencies;
+
+                async fn init(
+                    (): Self::PreInit,
+                    _dependencies: DependencyRefs<Self>,
+                ) -> Result<Self, module::InitError> {
+                    Ok($name)
+                }
+            }
+        };
+    }
+
+    dummy_module!(A, ());
+    dummy_module!(B, (A,));
+    dummy_module!(C, (A,));
+    dummy_module!(D, (B, C));
+
+    dummy_module!(X, (Y,));
+    dummy_module!(Y, (X,));
+
+    trait Unprovided {}
+    dummy_module!(NeedsInterface, (Dyn<dyn Unprovided>,));
+    dummy_module!(OptionallyNeedsA, (Option<A>,));
+
+    /// Sorts the builder's modules into batches of names
+    fn batches(builder: &RegistryBuilder) -> Result<Vec<Vec<&'static str>>, InitError> {
+        let interfaces = InterfaceMap::new();
+        let dependencies = resolve_dependencies(&builder.modules, &interfaces)?;
+        let batches = sort_into_batches(&builder.modules, &dependencies)?;
+        Ok(batches
+            .into_iter()
+            .map(|batch| {
+                let mut names: Vec<_> = batch
+                    .into_iter()
+                    .map(|index| builder.modules[index].name)
+                    .collect();
+                names.sort();
+                names
+            })
+            .collect())
+    }
+
+    #[test]
+    fn registers_dependencies_automatically() {
+        let mut builder = RegistryBuilder::new();
+        builder.register_module::<D>(());
+
+        let mut registered: Vec<_> = builder
+            .modules
+            .iter()
+            .map(|registration| (registration.name, registration.explicit))
+            .collect();
+        registered.sort();
+        let mut expected = vec![
+            (type_name::<A>(), false),
+            (type_name::<B>(), false),
+            (type_name::<C>(), false),
+            (type_name::<D>(), true),
+        ];
+        expected.sort();
+        assert_eq!(registered, expected);
+    }
+
+    #[test]
+    fn explicit_registration_replaces_automatic_one() {
+        let mut builder = RegistryBuilder::new();
+        builder.register_module::<B>(());
+        builder.register_module::<A>(());
+
+        assert_eq!(builder.modules.len(), 2);
+        assert!(
+            builder
+                .modules
+                .iter()
+                .all(|registration| registration.explicit)
+        );
+    }
+
+    #[test]
+    fn sorts_into_batches() {
+        let mut builder = RegistryBuilder::new();
+        builder.register_module::<D>(());
+
+        let mut middle = vec![type_name::<B>(), type_name::<C>()];
+        middle.sort();
+        assert_eq!(
+            batches(&builder).unwrap(),
+            vec![vec![type_name::<A>()], middle, vec![type_name::<D>()]]
+        );
+    }
+
+    #[test]
+    fn drops_unregistered_optional_dependencies() {
+        let mut builder = RegistryBuilder::new();
+        builder.register_module::<OptionallyNeedsA>(());
+        assert_eq!(builder.modules.len(), 1);
+        assert_eq!(
+            batches(&builder).unwrap(),
+            vec![vec![type_name::<OptionallyNeedsA>()]]
+        );
+
+        builder.register_module::<A>(());
+        assert_eq!(
+            batches(&builder).unwrap(),
+            vec![
+                vec![type_name::<A>()],
+                vec![type_name::<OptionallyNeedsA>()]
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let mut builder = RegistryBuilder::new();
+        builder.register_module::<X>(());
+
+        match batches(&builder) {
+            Err(InitError::DependencyCycle(cycle)) => {
+                assert_eq!(
+                    cycle,
+                    vec![type_name::<X>(), type_name::<Y>(), type_name::<X>()]
+                );
+            }
+            other => panic!("Expected a dependency cycle, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn detects_missing_dependencies() {
+        let mut builder = RegistryBuilder::new();
+        builder.register_module::<NeedsInterface>(());
+
+        match batches(&builder) {
+            Err(InitError::MissingDependency { module, dependency }) => {
+                assert_eq!(module, type_name::<NeedsInterface>());
+                assert_eq!(dependency, type_name::<dyn Unprovided>());
+            }
+            other => panic!("Expected a missing dependency, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn initializes_dependencies_first() {
+        let registry = RegistryBuilder::new()
+            .register_module::<D>(())
+            .build()
+            .await
+            .unwrap();
+
+        let position = |type_id: TypeId| {
+            registry
+                .init_order
+                .iter()
+                .position(|initialized| *initialized == type_id)
+                .unwrap()
+        };
+        let [a, b, c, d] = [
+            TypeId::of::<A>(),
+            TypeId::of::<B>(),
+            TypeId::of::<C>(),
+            TypeId::of::<D>(),
+        ]
+        .map(position);
+        assert!(a < b && a < c);
+        assert!(b < d && c < d);
+
+        registry
+            .scope(async {
+                D::global();
+            })
+            .await;
+    }
+}
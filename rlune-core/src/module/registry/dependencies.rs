@@ -4,52 +4,112 @@ use std::any::TypeId;
 use std::any::type_name;
 
 use crate::module::Module;
-use crate::module::registry::module_set::OwnedModulesSet;
+use crate::module::registry::builder::RegistryBuilder;
+use crate::module::registry::module_set::LeakedModuleSet;
 use crate::util_macros::impl_tuples;
-/// A tuple of [`Module`]s which need to be initialized before another one which depends on them.
+/// A tuple of [`ModuleDependency`]s which need to be initialized before another module which depends on them.
 pub trait ModuleDependencies: Sized + Send + Sync + 'static {
+    /// The tuple of references to the initialized dependencies passed to [`Module::init`]
+    type Refs: Send + 'static;
+
+    /// Calls `func` with the `TypeId`, name and optionality of every dependency
     #[doc(hidden)]
-    fn for_each(func: impl FnMut(TypeId, &'static str));
+    fn for_each(func: impl FnMut(TypeId, &'static str, bool));
 
     #[doc(hidden)]
-    fn take(modules: &mut OwnedModulesSet) -> Self;
+    fn register_defaults(builder: &mut RegistryBuilder);
 
     #[doc(hidden)]
-    fn put_back(self, modules: &mut OwnedModulesSet);
+    fn get(modules: &LeakedModuleSet) -> Self::Refs;
+}
+
+/// A single dependency of a module
+///
+/// This trait is implemented by:
+/// - every [`Module`] which is required to be present
+/// - [`Option`] of another `ModuleDependency` which is only used if it has been registered
+/// - [`Dyn`](crate::module::registry::Dyn) which depends on whatever module provides an interface
+pub trait ModuleDependency: Sized + Send + Sync + 'static {
+    /// The reference to the initialized dependency passed to [`Module::init`]
+    ///
+    /// For modules this is `&'static M`, so it may be stored in the depending module.
+    type Ref: Send + 'static;
+
+    /// Calls `func` with the `TypeId`, name and optionality of the dependency
+    #[doc(hidden)]
+    fn for_each(func: impl FnMut(TypeId, &'static str, bool));
+
+    #[doc(hidden)]
+    fn register_defaults(builder: &mut RegistryBuilder);
+
+    #[doc(hidden)]
+    fn try_get(modules: &LeakedModuleSet) -> Option<Self::Ref>;
+}
+
+impl<M: Module> ModuleDependency for M {
+    type Ref = &'static M;
+
+    fn for_each(mut func: impl FnMut(TypeId, &'static str, bool)) {
+        func(TypeId::of::<M>(), type_name::<M>(), false);
+    }
+
+    fn register_defaults(builder: &mut RegistryBuilder) {
+        builder.register_default::<M>();
+    }
+
+    fn try_get(modules: &LeakedModuleSet) -> Option<Self::Ref> {
+        modules.get::<M>()
+    }
+}
+
+impl<D: ModuleDependency> ModuleDependency for Option<D> {
+    type Ref = Option<D::Ref>;
+
+    fn for_each(mut func: impl FnMut(TypeId, &'static str, bool)) {
+        D::for_each(|type_id, name, _| func(type_id, name, true));
+    }
+
+    fn register_defaults(_builder: &mut RegistryBuilder) {}
+
+    fn try_get(modules: &LeakedModuleSet) -> Option<Self::Ref> {
+        Some(D::try_get(modules))
+    }
 }
 
 macro_rules! impl_module_dependencies {
     ($($T:ident),+) => {
-        impl<$( $T: Module, )+> ModuleDependencies for ($( $T, )+) {
-            fn for_each(mut func: impl FnMut(TypeId, &'static str)) {
+        impl<$( $T: ModuleDependency, )+> ModuleDependencies for ($( $T, )+) {
+            type Refs = ($( $T::Ref, )+);
+
+            fn for_each(mut func: impl FnMut(TypeId, &'static str, bool)) {
                 $(
-                    func(TypeId::of::<$T>(), type_name::<$T>());
+                    $T::for_each(&mut func);
                 )*
             }
 
-            fn take(modules: &mut OwnedModulesSet) -> Self {
+            fn register_defaults(builder: &mut RegistryBuilder) {
+                $(
+                    $T::register_defaults(builder);
+                )+
+            }
+
+            fn get(modules: &LeakedModuleSet) -> Self::Refs {
                 ($(
-                    *modules.remove::<$T>().unwrap_or_else(
+                    $T::try_get(modules).unwrap_or_else(
                         || panic!("Module {} has not been initialised yet", type_name::<$T>())
                     ),
                 )+)
             }
-
-            fn put_back(self, modules: &mut OwnedModulesSet) {
-                #[allow(non_snake_case)]
-                let ($( $T, )+) = self;
-                $(
-                    modules.insert($T);
-                )+
-            }
         }
     };
 }
 impl_tuples!(impl_module_dependencies);
 impl ModuleDependencies for () {
-    fn for_each(_func: impl FnMut(TypeId, &'static str)) {}
+    type Refs = ();
+
+    fn for_each(_func: impl FnMut(TypeId, &'static str, bool)) {}
 
-    fn take(_modules: &mut OwnedModulesSet) -> Self {}
+    fn register_defaults(_builder: &mut RegistryBuilder) {}
 
-    fn put_back(self, _modules: &mut OwnedModulesSet) {}
+    fn get(_modules: &LeakedModuleSet) -> Self::Refs {}
 }
@@ -0,0 +1,138 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use futures_concurrency::future::Join;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::module::registry::Registry;
+
+/// Whether a module is able to do its job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// The module works as expected
+    Healthy,
+
+    /// The module works but some non-essential functionality is impaired
+    Degraded,
+
+    /// The module is unable to do its job
+    Unhealthy,
+}
+
+/// Result of a [`Module::health_check`](crate::Module::health_check)
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct HealthCheck {
+    /// The module's health
+    pub status: HealthStatus,
+
+    /// Human-readable details explaining the status
+    pub details: Option<String>,
+}
+
+impl HealthCheck {
+    /// Constructs a [`HealthStatus::Healthy`] check without details
+    pub fn healthy() -> Self {
+        Self {
+            status: HealthStatus::Healthy,
+            details: None,
+        }
+    }
+
+    /// Constructs a [`HealthStatus::Degraded`] check
+    pub fn degraded(details: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Degraded,
+            details: Some(details.into()),
+        }
+    }
+
+    /// Constructs a [`HealthStatus::Unhealthy`] check
+    pub fn unhealthy(details: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Unhealthy,
+            details: Some(details.into()),
+        }
+    }
+}
+
+/// The health of a single module
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ModuleHealth {
+    /// The module's type name
+    pub name: &'static str,
+
+    /// The module's health check
+    #[serde(flatten)]
+    pub check: HealthCheck,
+}
+
+/// The combined health of every module
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct HealthReport {
+    /// The worst status of any module
+    pub status: HealthStatus,
+
+    /// The individual modules' health in the order they have been initialized
+    pub modules: Vec<ModuleHealth>,
+}
+
+impl Registry {
+    /// Runs every module's [`Module::health_check`](crate::Module::health_check) concurrently
+    ///
+    /// A check which doesn't finish within `timeout` is reported as [`HealthStatus::Unhealthy`].
+    #[instrument(level = "trace", name = "Registry::health_report", skip(self))]
+    pub async fn health_report(&'static self, timeout: Duration) -> HealthReport {
+        let checks = self
+            .init_order
+            .iter()
+            .filter_map(|type_id| self.modules.get_dyn(*type_id))
+            .map(|module| async move {
+                let mut handle = module.health_check();
+                let check = match tokio::time::timeout(timeout, &mut handle).await {
+                    Ok(Ok(check)) => check,
+                    Ok(Err(join_error)) => HealthCheck::unhealthy(format!(
+                        "The health check failed to complete: {join_error}"
+                    )),
+                    Err(_) => {
+                        handle.abort();
+                        HealthCheck::unhealthy(format!(
+                            "The health check didn't complete within {timeout:?}"
+                        ))
+                    }
+                };
+                ModuleHealth {
+                    name: module.name(),
+                    check,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let modules = checks.join().await;
+        HealthReport {
+            status: modules
+                .iter()
+                .map(|module| module.check.status)
+                .max()
+                .unwrap_or(HealthStatus::Healthy),
+            modules,
+        }
+    }
+
+    /// Marks the application as shutting down
+    ///
+    /// This is called as soon as a shutdown of the webserver is requested, before its connections are drained,
+    /// and makes the application report itself as "not ready".
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks whether the application is shutting down
+    ///
+    /// (see [`Registry::begin_shutdown`])
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+}
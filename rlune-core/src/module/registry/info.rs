@@ -0,0 +1,76 @@
+use std::fmt::Write;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::module::registry::Registry;
+
+/// Metadata about a module in the [`Registry`]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ModuleInfo {
+    /// The module's type name
+    pub name: &'static str,
+
+    /// The type names of the modules this one has been initialized after
+    ///
+    /// Interfaces are resolved to their providers
+    /// and optional dependencies which haven't been registered are omitted.
+    pub dependencies: Vec<&'static str>,
+
+    /// The `Debug` output of the module's setup
+    ///
+    /// `None` if the module has been registered as an already initialized instance.
+    pub setup: Option<String>,
+
+    /// Whether the module has been registered automatically as someone's dependency
+    pub automatic: bool,
+
+    /// The time each initialization phase took
+    pub timings: PhaseTimings,
+}
+
+/// The time each initialization phase of a module took
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct PhaseTimings {
+    /// Duration of [`Module::pre_init`](crate::Module::pre_init)
+    pub pre_init: Option<Duration>,
+
+    /// Duration of [`Module::init`](crate::Module::init)
+    pub init: Option<Duration>,
+
+    /// Duration of [`Module::post_init`](crate::Module::post_init)
+    pub post_init: Option<Duration>,
+}
+
+impl Registry {
+    /// Returns metadata about every module in the order they have been initialized
+    pub fn module_infos(&self) -> Vec<ModuleInfo> {
+        self.infos
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Renders the module dependency graph in Graphviz' dot language
+    ///
+    /// Edges point from a module to its dependencies.
+    /// Automatically registered modules are drawn dashed.
+    pub fn module_graph_dot(&self) -> String {
+        let infos = self.module_infos();
+
+        let mut dot = String::from("digraph modules {\n");
+        for info in &infos {
+            let style = if info.automatic { ", style=dashed" } else { "" };
+            let _ = writeln!(dot, "    {:?} [shape=box{style}];", info.name);
+        }
+        for info in &infos {
+            for dependency in &info.dependencies {
+                let _ = writeln!(dot, "    {:?} -> {dependency:?};", info.name);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,148 @@
+#![allow(private_interfaces)]
+
+use std::any::Any;
+use std::any::TypeId;
+use std::any::type_name;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+
+use crate::module::Module;
+use crate::module::TryGlobalError;
+use crate::module::registry::DynModule;
+use crate::module::registry::ModuleDependency;
+use crate::module::registry::Registry;
+use crate::module::registry::builder::RegistryBuilder;
+use crate::module::registry::module_set::LeakedModuleSet;
+use crate::module::registry::module_set::downcast_ref;
+
+/// A [`Module`] which can be registered as provider of the interface `I`
+///
+/// `I` is usually a trait object like `dyn Mailer`.
+/// Other modules can then depend on "some module providing `I`" using [`Dyn<I>`]
+/// without knowing which module the application author chose.
+///
+/// ```ignore
+/// impl ProvidesInterface<dyn Mailer> for SmtpMailer {
+///     fn as_interface(&self) -> &(dyn Mailer + 'static) {
+///         self
+///     }
+/// }
+///
+/// registry.register_module::<SmtpMailer>(setup);
+/// registry.register_interface::<dyn Mailer, SmtpMailer>();
+/// ```
+pub trait ProvidesInterface<I: ?Sized + 'static>: Module {
+    /// Casts the module into its interface
+    fn as_interface(&self) -> &I;
+}
+
+/// A dependency on whichever module has been registered as provider of the interface `I`
+///
+/// (see [`ProvidesInterface`])
+///
+/// It dereferences to `I` and may be stored by the module depending on it.
+/// Other code can access the provider using [`Dyn::global`].
+pub struct Dyn<I: ?Sized + 'static> {
+    module: &'static dyn DynModule,
+    cast: Cast<I>,
+}
+
+/// Function casting a type erased module into the interface `I`
+type Cast<I> = fn(&dyn DynModule) -> &I;
+
+impl<I: ?Sized + 'static> Dyn<I> {
+    /// Gets the global instance of the module providing `I`
+    ///
+    /// # Panics
+    /// If no module providing `I` has been initialized yet.
+    pub fn global() -> &'static I {
+        Self::try_global().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Gets the global instance of the module providing `I`
+    ///
+    /// # Errors
+    /// If no module providing `I` has been initialized yet.
+    pub fn try_global() -> Result<&'static I, TryGlobalError> {
+        Registry::try_global()
+            .ok_or(TryGlobalError::Registry)?
+            .try_get_interface()
+            .ok_or_else(|| TryGlobalError::Interface {
+                interface_type: type_name::<I>(),
+            })
+    }
+}
+
+impl<I: ?Sized + 'static> Deref for Dyn<I> {
+    type Target = I;
+
+    fn deref(&self) -> &Self::Target {
+        (self.cast)(self.module)
+    }
+}
+
+impl<I: ?Sized + 'static> Clone for Dyn<I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I: ?Sized + 'static> Copy for Dyn<I> {}
+
+impl<I: ?Sized + 'static> fmt::Debug for Dyn<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Dyn<{}>({})", type_name::<I>(), self.module.name())
+    }
+}
+
+impl<I: ?Sized + 'static> ModuleDependency for Dyn<I> {
+    type Ref = Self;
+
+    fn for_each(mut func: impl FnMut(TypeId, &'static str, bool)) {
+        func(TypeId::of::<I>(), type_name::<I>(), false);
+    }
+
+    fn register_defaults(_builder: &mut RegistryBuilder) {}
+
+    fn try_get(modules: &LeakedModuleSet) -> Option<Self::Ref> {
+        let interface = modules.interfaces().get(&TypeId::of::<I>())?;
+        let cast = *interface.cast.downcast_ref::<Cast<I>>()?;
+        let module = modules.get_dyn(interface.module)?;
+        Some(Self { module, cast })
+    }
+}
+
+/// A module registered as provider of an interface
+pub(crate) struct Interface {
+    /// The `TypeId` of the providing module
+    pub module: TypeId,
+
+    /// The [`Cast`] from the module to the interface
+    pub cast: Box<dyn Any + Send + Sync>,
+}
+
+impl Interface {
+    pub fn new<I: ?Sized + 'static, M: ProvidesInterface<I>>() -> Self {
+        Self {
+            module: TypeId::of::<M>(),
+            cast: Box::new(cast::<I, M> as Cast<I>),
+        }
+    }
+}
+
+/// Maps the `TypeId`s of interfaces to the modules providing them
+pub(crate) type InterfaceMap = HashMap<TypeId, Interface>;
+
+fn cast<I: ?Sized + 'static, M: ProvidesInterface<I>>(module: &dyn DynModule) -> &I {
+    downcast_ref::<M>(module).as_interface()
+}
+
+impl Registry {
+    /// Gets the module providing the interface `I`
+    pub fn try_get_interface<I: ?Sized + 'static>(&'static self) -> Option<&'static I> {
+        let interface = self.interfaces.get(&TypeId::of::<I>())?;
+        let cast = interface.cast.downcast_ref::<Cast<I>>()?;
+        Some(cast(self.modules.get_dyn(interface.module)?))
+    }
+}
@@ -1,28 +1,81 @@
 use std::any::Any;
+use std::any::TypeId;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::OnceLock;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
 use tokio::task::JoinHandle;
 
 pub use self::dependencies::ModuleDependencies;
+pub use self::dependencies::ModuleDependency;
+pub use self::health::HealthCheck;
+pub use self::health::HealthReport;
+pub use self::health::HealthStatus;
+pub use self::health::ModuleHealth;
+pub use self::info::ModuleInfo;
+pub use self::info::PhaseTimings;
+pub use self::interface::Dyn;
+pub use self::interface::ProvidesInterface;
 use crate::module;
 use crate::module::Module;
 use crate::module::registry::builder::RegistryBuilder;
+use crate::module::registry::interface::InterfaceMap;
 use crate::module::registry::module_set::LeakedModuleSet;
+use crate::module::registry::tasks::Tasks;
 
 pub mod builder;
 mod dependencies;
+mod health;
+mod info;
+mod interface;
 mod module_set;
+pub mod reload;
+mod scope;
+pub mod shutdown;
+pub mod tasks;
 
 /// The registry stores [`Module`]s
 ///
 /// is responsible for their initialization and grants access to them.
 pub struct Registry {
     modules: LeakedModuleSet,
+
+    /// The modules' `TypeId`s in the order they have been initialized
+    init_order: Vec<TypeId>,
+
+    /// The time each module is granted to shut down
+    shutdown_timeout: Duration,
+
+    /// Maps interfaces to the modules providing them
+    interfaces: Arc<InterfaceMap>,
+
+    /// Metadata about the modules in the order they have been initialized
+    infos: Mutex<Vec<ModuleInfo>>,
+
+    /// Set once the application starts shutting down
+    shutting_down: AtomicBool,
+
+    /// The supervisor of background tasks spawned by modules
+    tasks: Tasks,
 }
 
 trait DynModule: Any + Send + Sync + 'static {
+    #[doc(hidden)]
+    fn name(&self) -> &'static str;
+
     #[doc(hidden)]
     fn post_init(&'static self) -> JoinHandle<Result<(), module::PostInitError>>;
+
+    #[doc(hidden)]
+    fn shutdown(&'static self) -> JoinHandle<Result<(), module::ShutdownError>>;
+
+    #[doc(hidden)]
+    fn reload(&'static self) -> JoinHandle<Result<(), module::ReloadError>>;
+
+    #[doc(hidden)]
+    fn health_check(&'static self) -> JoinHandle<HealthCheck>;
 }
 
 impl Registry {
@@ -30,16 +83,23 @@ impl Registry {
         RegistryBuilder::new()
     }
 
+    /// Gets the current registry
+    ///
+    /// This is the registry entered using [`Registry::scope`] if any
+    /// and the one initialized by [`RegistryBuilder::init`] otherwise.
     #[track_caller]
     pub fn global() -> &'static Self {
-        let Some(global) = Self::raw_global().get() else {
+        let Some(global) = Self::try_global() else {
             panic!("The global registry has not been initialized yet.");
         };
         global
     }
 
+    /// Gets the current registry
+    ///
+    /// (see [`Registry::global`])
     pub fn try_global() -> Option<&'static Self> {
-        Self::raw_global().get()
+        Self::scoped().or_else(|| Self::raw_global().get())
     }
 
     pub fn try_get<T: Module>(&self) -> Option<&T> {
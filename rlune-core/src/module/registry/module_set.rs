@@ -2,14 +2,16 @@ use std::any::TypeId;
 use std::collections::HashMap;
 use std::hash::BuildHasher;
 use std::hash::Hasher;
+use std::sync::Arc;
 
 use crate::module::Module;
 use crate::module::registry::DynModule;
+use crate::module::registry::interface::InterfaceMap;
 
 pub struct ModuleSet<M> {
     set: HashMap<TypeId, M, BuildXorHasher>,
+    interfaces: Arc<InterfaceMap>,
 }
-pub type OwnedModulesSet = ModuleSet<Box<dyn DynModule>>;
 pub type LeakedModuleSet = ModuleSet<&'static dyn DynModule>;
 
 impl<M> Default for ModuleSet<M> {
@@ -20,56 +22,42 @@ impl<M> Default for ModuleSet<M> {
 
 impl<M> ModuleSet<M> {
     pub fn new() -> Self {
+        Self::with_interfaces(Default::default())
+    }
+
+    /// Constructs an empty set which knows the providers of interfaces
+    pub(crate) fn with_interfaces(interfaces: Arc<InterfaceMap>) -> Self {
         Self {
             set: HashMap::with_hasher(BuildXorHasher),
+            interfaces,
         }
     }
-}
 
-impl OwnedModulesSet {
-    pub fn insert<T: Module>(&mut self, value: T) -> Option<Box<T>> {
-        self.set
-            .insert(TypeId::of::<T>(), Box::new(value))
-            .map(downcast_box)
+    /// Maps interfaces to the modules providing them
+    pub(crate) fn interfaces(&self) -> &InterfaceMap {
+        &self.interfaces
     }
+}
 
-    pub fn remove<T: Module>(&mut self) -> Option<Box<T>> {
-        self.set.remove(&TypeId::of::<T>()).map(downcast_box)
+impl LeakedModuleSet {
+    pub fn insert_dyn(&mut self, type_id: TypeId, module: &'static dyn DynModule) {
+        self.set.insert(type_id, module);
     }
 
-    pub fn leak(self) -> LeakedModuleSet {
-        let mut modules = LeakedModuleSet::new();
-        for (type_id, module) in self.set {
-            modules.set.insert(type_id, Box::leak(module));
-        }
-        modules
-    }
-}
-impl LeakedModuleSet {
     pub fn get<T: Module>(&self) -> Option<&'static T> {
         self.set.get(&TypeId::of::<T>()).copied().map(downcast_ref)
     }
 
+    pub fn get_dyn(&self, type_id: TypeId) -> Option<&'static dyn DynModule> {
+        self.set.get(&type_id).copied()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &'static dyn DynModule> + use<'_> {
         self.set.values().copied()
     }
 }
 
-fn downcast_box<T: Module>(module: Box<dyn DynModule>) -> Box<T> {
-    if module.as_ref().type_id() != TypeId::of::<T>() {
-        // The two places calling this method ensure `T` to be correct
-        unreachable!()
-    }
-
-    // SAFETY: just checked whether we are pointing to the correct type, and we can rely on
-    // that check for memory safety because rust has implemented Any for all types; no other
-    // impls can exist as they would conflict with their impl.
-    unsafe {
-        let raw = Box::into_raw(module);
-        Box::from_raw(raw as *mut T)
-    }
-}
-fn downcast_ref<T: Module>(module: &dyn DynModule) -> &T {
+pub(super) fn downcast_ref<T: Module>(module: &dyn DynModule) -> &T {
     if module.type_id() != TypeId::of::<T>() {
         unreachable!()
     }
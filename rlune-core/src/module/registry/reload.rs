@@ -0,0 +1,71 @@
+use std::error::Error;
+use std::fmt;
+
+use tracing::info;
+use tracing::instrument;
+use tracing::warn;
+
+use crate::module;
+use crate::module::registry::Registry;
+use crate::module::registry::builder::join_error_into;
+
+impl Registry {
+    /// Reloads all modules
+    ///
+    /// Every module's [`Module::reload`](crate::Module::reload) is run
+    /// sequentially in initialization order.
+    /// A failing module doesn't prevent the remaining ones from reloading.
+    #[instrument(level = "trace", name = "Registry::reload", skip(self))]
+    pub async fn reload(&'static self) -> Result<(), ReloadError> {
+        let mut errors = Vec::new();
+        for type_id in &self.init_order {
+            let module = self
+                .modules
+                .get_dyn(*type_id)
+                .unwrap_or_else(|| unreachable!("Every initialized module is in the registry"));
+
+            let result = module
+                .reload()
+                .await
+                .unwrap_or_else(|join_error| Err(join_error_into(join_error)));
+
+            if let Err(error) = result {
+                warn!(
+                    module.name = module.name(),
+                    error.display = %error,
+                    error.debug = ?error,
+                    "Module failed to reload"
+                );
+                errors.push(error);
+            }
+        }
+
+        if errors.is_empty() {
+            info!("Reloaded modules");
+            Ok(())
+        } else {
+            Err(ReloadError(errors))
+        }
+    }
+}
+
+/// Error returned by [`Registry::reload`]
+///
+/// It contains the errors of every module which failed to reload.
+#[derive(Debug)]
+pub struct ReloadError(pub Vec<module::ReloadError>);
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, rest) = self
+            .0
+            .split_first()
+            .unwrap_or_else(|| unreachable!("Error lists should not be empty"));
+        write!(f, "Error during module reload: {first}")?;
+        if !rest.is_empty() {
+            write!(f, " (and {} more...)", rest.len())?;
+        }
+        Ok(())
+    }
+}
+impl Error for ReloadError {}
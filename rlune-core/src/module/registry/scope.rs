@@ -0,0 +1,56 @@
+use std::future::Future;
+
+use crate::module::registry::Registry;
+
+tokio::task_local! {
+    /// The registry entered using [`Registry::scope`]
+    static SCOPED: &'static Registry;
+}
+
+impl Registry {
+    /// Runs a future with `self` as the registry
+    /// [`Registry::global`] and [`Module::global`](crate::Module::global) resolve to
+    ///
+    /// This is used by tests to run handlers against an isolated registry
+    /// constructed by [`RegistryBuilder::build`](crate::module::registry::builder::RegistryBuilder::build).
+    /// Several scopes with different registries can be active in parallel on different tasks.
+    ///
+    /// The scope is not inherited by tasks spawned within it.
+    /// Wrap their futures in another `scope` if they need to access modules.
+    ///
+    /// ```ignore
+    /// #[tokio::test]
+    /// async fn test_handler() {
+    ///     let registry = Registry::builder()
+    ///         .register_instance(AuthModule::for_tests())
+    ///         .build()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     registry
+    ///         .scope(async {
+    ///             // `AuthModule::global()` returns the instance registered above
+    ///         })
+    ///         .await;
+    /// }
+    /// ```
+    pub async fn scope<F: Future>(&'static self, future: F) -> F::Output {
+        SCOPED.scope(self, future).await
+    }
+
+    /// Gets the registry entered by the current task using [`Registry::scope`]
+    pub fn scoped() -> Option<&'static Self> {
+        SCOPED.try_with(|registry| *registry).ok()
+    }
+}
+
+/// Wraps a future to be spawned in the registry scope of the current task, if any
+pub(crate) fn propagate_scope<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let registry = Registry::scoped();
+    async move {
+        match registry {
+            Some(registry) => registry.scope(future).await,
+            None => future.await,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fmt;
+
+use tracing::instrument;
+use tracing::warn;
+
+use crate::module;
+use crate::module::registry::Registry;
+use crate::module::registry::builder::join_error_into;
+
+impl Registry {
+    /// Shuts down all modules
+    ///
+    /// First, every [background task](Registry::tasks) is cancelled.
+    /// Afterward, every module's [`Module::shutdown`](crate::Module::shutdown) is run
+    /// sequentially in reverse initialization order.
+    /// A failing module doesn't prevent the remaining ones from shutting down.
+    #[instrument(level = "trace", name = "Registry::shutdown", skip(self))]
+    pub async fn shutdown(&'static self) -> Result<(), ShutdownError> {
+        self.begin_shutdown();
+        self.tasks.cancel(self.shutdown_timeout).await;
+
+        let mut errors = Vec::new();
+        for type_id in self.init_order.iter().rev() {
+            let module = self
+                .modules
+                .get_dyn(*type_id)
+                .unwrap_or_else(|| unreachable!("Every initialized module is in the registry"));
+
+            let mut handle = module.shutdown();
+            let result = match tokio::time::timeout(self.shutdown_timeout, &mut handle).await {
+                Ok(join_result) => {
+                    join_result.unwrap_or_else(|join_error| Err(join_error_into(join_error)))
+                }
+                Err(_) => {
+                    handle.abort();
+                    Err(format!(
+                        "Module '{}' didn't shut down within {:?}",
+                        module.name(),
+                        self.shutdown_timeout
+                    )
+                    .into())
+                }
+            };
+
+            if let Err(error) = result {
+                warn!(
+                    module.name = module.name(),
+                    error.display = %error,
+                    error.debug = ?error,
+                    "Module failed to shut down"
+                );
+                errors.push(error);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ShutdownError(errors))
+        }
+    }
+}
+
+/// Error returned by [`Registry::shutdown`]
+///
+/// It contains the errors of every module which failed to shut down.
+#[derive(Debug)]
+pub struct ShutdownError(pub Vec<module::ShutdownError>);
+
+impl fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, rest) = self
+            .0
+            .split_first()
+            .unwrap_or_else(|| unreachable!("Error lists should not be empty"));
+        write!(f, "Error during module shutdown: {first}")?;
+        if !rest.is_empty() {
+            write!(f, " (and {} more...)", rest.len())?;
+        }
+        Ok(())
+    }
+}
+impl Error for ShutdownError {}
@@ -0,0 +1,377 @@
+//! Background tasks supervised by the [`Registry`]
+//!
+//! Modules should spawn their long-running work (cleaning up expired data, polling other services, ...)
+//! using [`Registry::tasks`] instead of `tokio::spawn`, preferably in their `post_init`:
+//!
+//! ```ignore
+//! async fn post_init(&'static self) -> Result<(), PostInitError> {
+//!     Registry::global().tasks().spawn_periodic(
+//!         "token-sweeper",
+//!         Duration::from_secs(60),
+//!         RestartPolicy::OnPanic,
+//!         move || self.delete_expired_tokens(),
+//!     );
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Supervised tasks
+//! - run inside a tracing span carrying their name
+//! - can be restarted if they panic (see [`RestartPolicy`])
+//! - are cancelled during shutdown before any module's [`Module::shutdown`](crate::Module::shutdown) runs
+//! - are listed by [`Registry::task_infos`]
+
+use std::any::Any;
+use std::error::Error;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use futures_concurrency::future::Join;
+use futures_lite::FutureExt;
+use futures_lite::future;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::Instrument;
+use tracing::debug;
+use tracing::error;
+use tracing::info_span;
+use tracing::warn;
+
+use crate::module::registry::Registry;
+use crate::module::registry::scope::propagate_scope;
+
+/// Error a task may return
+pub type TaskError = Box<dyn Error + Send + Sync + 'static>;
+
+/// The time to wait before restarting a panicked task
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// What to do when a supervised task panics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum RestartPolicy {
+    /// The task stays stopped
+    Never,
+
+    /// The task is restarted after a short delay
+    ///
+    /// Periodic tasks simply continue with their next run.
+    OnPanic,
+}
+
+/// The state of a supervised task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum TaskStatus {
+    /// The task is running or waiting for its next run
+    Running,
+
+    /// The task returned successfully
+    Finished,
+
+    /// The task returned an error
+    Failed,
+
+    /// The task panicked and won't be restarted
+    Panicked,
+
+    /// The task has been stopped during shutdown
+    Cancelled,
+}
+
+/// Metadata about a supervised task
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TaskInfo {
+    /// The name the task has been spawned with
+    pub name: String,
+
+    /// The interval a periodic task is run at
+    pub period: Option<Duration>,
+
+    /// What happens when the task panics
+    pub restart: RestartPolicy,
+
+    /// The task's current state
+    pub status: TaskStatus,
+
+    /// The number of times the task has been restarted after a panic
+    pub restarts: u32,
+
+    /// The last error or panic message
+    pub last_error: Option<String>,
+}
+
+/// Handle passed to tasks to observe their cancellation
+#[derive(Debug, Clone)]
+pub struct Cancellation(watch::Receiver<bool>);
+
+impl Cancellation {
+    /// Checks whether the task should stop
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once the task should stop
+    ///
+    /// A long-running task should regularly race its work against this future
+    /// and return once it resolves.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.0.clone();
+        // An error means the sender has been dropped with the registry which never happens
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// The supervisor owning every background task
+///
+/// (see [module level docs](self))
+pub struct Tasks {
+    cancel: watch::Sender<bool>,
+    tasks: Mutex<Vec<Task>>,
+}
+
+/// A spawned task
+struct Task {
+    info: Arc<Mutex<TaskInfo>>,
+
+    /// Taken when the task is cancelled
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Registry {
+    /// Gets the supervisor of the registry's background tasks
+    pub fn tasks(&'static self) -> &'static Tasks {
+        &self.tasks
+    }
+
+    /// Returns metadata about every background task in the order they have been spawned
+    pub fn task_infos(&self) -> Vec<TaskInfo> {
+        self.tasks
+            .tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|task| {
+                task.info
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone()
+            })
+            .collect()
+    }
+}
+
+impl Tasks {
+    /// Constructs a new supervisor without any tasks
+    pub(crate) fn new() -> Self {
+        Self {
+            cancel: watch::Sender::new(false),
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Spawns a long-running task
+    ///
+    /// The task should return once its [`Cancellation`] resolves.
+    /// If it is restarted, `task` is called again to construct a new future.
+    pub fn spawn<Fut>(
+        &'static self,
+        name: impl Into<String>,
+        restart: RestartPolicy,
+        mut task: impl FnMut(Cancellation) -> Fut + Send + 'static,
+    ) where
+        Fut: Future<Output = Result<(), TaskError>> + Send + 'static,
+    {
+        let info = Arc::new(Mutex::new(TaskInfo {
+            name: name.into(),
+            period: None,
+            restart,
+            status: TaskStatus::Running,
+            restarts: 0,
+            last_error: None,
+        }));
+        let cancellation = Cancellation(self.cancel.subscribe());
+
+        self.supervise(info.clone(), async move {
+            loop {
+                let result = AssertUnwindSafe(task(cancellation.clone()))
+                    .catch_unwind()
+                    .await;
+                let status = match result {
+                    Ok(Ok(())) if cancellation.is_cancelled() => TaskStatus::Cancelled,
+                    Ok(Ok(())) => TaskStatus::Finished,
+                    Ok(Err(error)) => {
+                        error!(
+                            error.display = %error,
+                            error.debug = ?error,
+                            "Task failed"
+                        );
+                        set_error(&info, error.to_string());
+                        TaskStatus::Failed
+                    }
+                    Err(panic) => {
+                        set_error(&info, panic_message(panic));
+                        if restart == RestartPolicy::OnPanic && !cancellation.is_cancelled() {
+                            tokio::time::sleep(RESTART_DELAY).await;
+                            info.lock().unwrap_or_else(PoisonError::into_inner).restarts += 1;
+                            debug!("Restarting task");
+                            continue;
+                        }
+                        TaskStatus::Panicked
+                    }
+                };
+                info.lock().unwrap_or_else(PoisonError::into_inner).status = status;
+                break;
+            }
+        });
+    }
+
+    /// Spawns a task which runs `task` every `period`
+    ///
+    /// The first run happens immediately.
+    /// Errors are logged and don't stop the task.
+    /// A run which is still in progress when the task is cancelled is allowed to finish.
+    pub fn spawn_periodic<Fut>(
+        &'static self,
+        name: impl Into<String>,
+        period: Duration,
+        restart: RestartPolicy,
+        mut task: impl FnMut() -> Fut + Send + 'static,
+    ) where
+        Fut: Future<Output = Result<(), TaskError>> + Send + 'static,
+    {
+        let info = Arc::new(Mutex::new(TaskInfo {
+            name: name.into(),
+            period: Some(period),
+            restart,
+            status: TaskStatus::Running,
+            restarts: 0,
+            last_error: None,
+        }));
+        let cancellation = Cancellation(self.cancel.subscribe());
+
+        self.supervise(info.clone(), async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                let cancelled = future::or(
+                    async {
+                        cancellation.cancelled().await;
+                        true
+                    },
+                    async {
+                        interval.tick().await;
+                        false
+                    },
+                )
+                .await;
+                if cancelled {
+                    break;
+                }
+
+                match AssertUnwindSafe(task()).catch_unwind().await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => {
+                        warn!(
+                            error.display = %error,
+                            error.debug = ?error,
+                            "Periodic task failed"
+                        );
+                        set_error(&info, error.to_string());
+                    }
+                    Err(panic) => {
+                        set_error(&info, panic_message(panic));
+                        if restart == RestartPolicy::Never {
+                            info.lock().unwrap_or_else(PoisonError::into_inner).status =
+                                TaskStatus::Panicked;
+                            return;
+                        }
+                        info.lock().unwrap_or_else(PoisonError::into_inner).restarts += 1;
+                    }
+                }
+            }
+            info.lock().unwrap_or_else(PoisonError::into_inner).status = TaskStatus::Cancelled;
+        });
+    }
+
+    /// Spawns the supervising future and stores it alongside the task's info
+    fn supervise(
+        &'static self,
+        info: Arc<Mutex<TaskInfo>>,
+        supervisor: impl Future<Output = ()> + Send + 'static,
+    ) {
+        let name = info
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .name
+            .clone();
+
+        if *self.cancel.borrow() {
+            debug!(task.name = %name, "Not spawning task during shutdown");
+            info.lock().unwrap_or_else(PoisonError::into_inner).status = TaskStatus::Cancelled;
+            return;
+        }
+
+        let handle = tokio::spawn(propagate_scope(
+            supervisor.instrument(info_span!("task", task.name = %name)),
+        ));
+        self.tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Task {
+                info,
+                handle: Some(handle),
+            });
+    }
+
+    /// Cancels every task and waits for them to stop
+    ///
+    /// Tasks which don't stop within `timeout` are aborted.
+    pub(crate) async fn cancel(&self, timeout: Duration) {
+        self.cancel.send_replace(true);
+
+        let handles = self
+            .tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter_mut()
+            .filter_map(|task| Some((task.info.clone(), task.handle.take()?)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|(info, mut handle)| async move {
+                if tokio::time::timeout(timeout, &mut handle).await.is_err() {
+                    handle.abort();
+                    let mut info = info.lock().unwrap_or_else(PoisonError::into_inner);
+                    info.status = TaskStatus::Cancelled;
+                    warn!(task.name = %info.name, "Task didn't stop within {timeout:?}");
+                }
+            })
+            .collect::<Vec<_>>()
+            .join()
+            .await;
+    }
+}
+
+/// Stores an error message in a task's info
+fn set_error(info: &Mutex<TaskInfo>, message: String) {
+    info.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .last_error = Some(message);
+}
+
+/// Extracts the message from a panic's payload and logs it
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic payload".to_string());
+    error!(panic.message = message, "Task panicked");
+    message
+}
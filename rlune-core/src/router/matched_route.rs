@@ -0,0 +1,156 @@
+//! The route a request has been routed to
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::extract::FromRequestParts;
+use axum::extract::MatchedPath;
+use axum::extract::OptionalFromRequestParts;
+use axum::extract::Request;
+use axum::extract::State;
+use axum::http::Method;
+use axum::http::request::Parts;
+
+use crate::handler::HandlerMeta;
+use crate::router::RluneRoute;
+use crate::router::RouteMetadataSet;
+use crate::stuff::api_error::ApiError;
+
+/// The route a request has been routed to
+///
+/// The router returned by [`RluneRouter::finish`](crate::RluneRouter::finish)
+/// adds it to the extensions of every request matching a handler.
+/// This happens before any layer added to a [`RluneRouter`](crate::RluneRouter) runs,
+/// so middleware can base its behaviour on the route's metadata:
+///
+/// ```ignore
+/// async fn timeout_middleware(request: Request, next: Next) -> Response {
+///     let timeout = request
+///         .extensions()
+///         .get::<MatchedRoute>()
+///         .and_then(|route| route.metadata().get::<TimeoutMetadata>())
+///         .map(|metadata| metadata.timeout)
+///         .unwrap_or(DEFAULT_TIMEOUT);
+///     // ...
+/// }
+/// ```
+///
+/// Handlers can take it as argument:
+///
+/// ```ignore
+/// #[get("/users/{uuid}")]
+/// async fn get_user(route: MatchedRoute, Path(uuid): Path<Uuid>) -> ApiResult<ApiJson<User>> {
+///     debug!(route.path = route.path(), handler = route.handler().ident, "Getting user");
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MatchedRoute(Arc<RluneRoute>);
+
+impl MatchedRoute {
+    /// Meta information about the route's handler
+    pub fn handler(&self) -> &HandlerMeta {
+        &self.0.handler
+    }
+
+    /// The route's path template (for example `/users/{uuid}`) including the paths it has been nested at
+    pub fn path(&self) -> &str {
+        &self.0.path
+    }
+
+    /// The metadata attached to the route
+    pub fn metadata(&self) -> &RouteMetadataSet {
+        &self.0.extensions
+    }
+}
+
+impl<S: Sync> FromRequestParts<S> for MatchedRoute {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().cloned().ok_or_else(|| {
+            ApiError::server_error("The request has not been routed by a finished RluneRouter")
+        })
+    }
+}
+
+impl<S: Sync> OptionalFromRequestParts<S> for MatchedRoute {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned())
+    }
+}
+
+/// All routes sharing the path template a request has been routed to
+///
+/// Unlike the [`MatchedRoute`], it is added to a request's extensions
+/// even if no handler has been registered for the request's method.
+/// This allows middleware to answer requests about the path as a whole,
+/// for example CORS preflight requests.
+#[derive(Debug, Clone)]
+pub struct PathRoutes(Arc<HashMap<Method, MatchedRoute>>);
+
+impl PathRoutes {
+    /// Gets the route handling `method`
+    pub fn get(&self, method: &Method) -> Option<&MatchedRoute> {
+        self.0.get(method)
+    }
+
+    /// Iterates over the routes and the methods they handle
+    pub fn iter(&self) -> impl Iterator<Item = (&Method, &MatchedRoute)> {
+        self.0.iter()
+    }
+}
+
+/// Lookup of the routes by their path
+pub(crate) type MatchedRoutes = HashMap<String, PathRoutes>;
+
+/// Builds the lookup used by [`insert_matched_route`]
+pub(crate) fn matched_routes(routes: &[RluneRoute]) -> MatchedRoutes {
+    let mut by_path = HashMap::<String, HashMap<Method, MatchedRoute>>::new();
+    for route in routes {
+        by_path.entry(route.path.clone()).or_default().insert(
+            route.handler.method.clone(),
+            MatchedRoute(Arc::new(route.clone())),
+        );
+    }
+    by_path
+        .into_iter()
+        .map(|(path, methods)| (path, PathRoutes(Arc::new(methods))))
+        .collect()
+}
+
+/// Adds the [`MatchedRoute`] and [`PathRoutes`] to a request's extensions
+pub(crate) async fn insert_matched_route(
+    State(routes): State<Arc<MatchedRoutes>>,
+    mut request: Request,
+) -> Request {
+    let Some(path_routes) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .and_then(|path| routes.get(path.as_str()))
+        .cloned()
+    else {
+        return request;
+    };
+
+    let route = path_routes
+        .get(request.method())
+        .or_else(|| {
+            // axum routes `HEAD` requests to `GET` handlers
+            (request.method() == Method::HEAD)
+                .then(|| path_routes.get(&Method::GET))
+                .flatten()
+        })
+        .cloned();
+    if let Some(route) = route {
+        request.extensions_mut().insert(route);
+    }
+    request.extensions_mut().insert(path_routes);
+    request
+}
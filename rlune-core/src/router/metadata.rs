@@ -69,6 +69,18 @@ pub struct RouteMetadataSet {
     extensions: Vec<Box<dyn DynRouteMetadata>>,
 }
 
+impl Clone for RouteMetadataSet {
+    fn clone(&self) -> Self {
+        Self {
+            extensions: self
+                .extensions
+                .iter()
+                .map(|ext| ext.clone_boxed())
+                .collect(),
+        }
+    }
+}
+
 impl RouteMetadataSet {
     /// Inserts some [`RouteMetadata`].
     ///
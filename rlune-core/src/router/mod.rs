@@ -1,17 +1,22 @@
 use std::convert::Infallible;
+use std::sync::Arc;
 
 use axum::extract::Request;
+use axum::middleware;
 use axum::response::IntoResponse;
 use axum::routing::Route;
 use axum::routing::Router;
 use tower::Layer;
 use tower::Service;
 
+pub use self::matched_route::MatchedRoute;
+pub use self::matched_route::PathRoutes;
 pub use self::metadata::RouteMetadata;
 pub use self::metadata::RouteMetadataSet;
 use crate::handler::HandlerMeta;
 use crate::handler::RluneHandler;
 
+mod matched_route;
 mod metadata;
 
 /// An `RluneRouter` combines several [`SwaggapiHandler`] under a common path.
@@ -75,8 +80,16 @@ impl RluneRouter {
         self.handlers.push(handler);
     }
 
+    /// Converts the router into an axum router and the list of its routes
+    ///
+    /// The axum router adds the [`MatchedRoute`] to every request's extensions.
     pub fn finish(self) -> (Router, Vec<RluneRoute>) {
-        (self.router, self.handlers)
+        let matched_routes = Arc::new(matched_route::matched_routes(&self.handlers));
+        let router = self.router.layer(middleware::map_request_with_state(
+            matched_routes,
+            matched_route::insert_matched_route,
+        ));
+        (router, self.handlers)
     }
 
     /// Calls [`Router::nest`] while preserving api information
@@ -149,7 +162,7 @@ impl RluneRouter {
 /// A route associates a url and method with a handler
 ///
 /// It also stores extensions which can be used for reflection.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RluneRoute {
     /// Meta information about the route's handler
     ///
@@ -0,0 +1,277 @@
+//! A stateless session "store" keeping the entire session in an encrypted cookie
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::task::Context;
+use std::task::Poll;
+
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
+use axum::http::Request;
+use axum::http::Response;
+use axum::http::StatusCode;
+use axum::http::header;
+use tower::Layer;
+use tower::Service;
+use tower_sessions::Expiry;
+use tower_sessions::Session;
+use tower_sessions::SessionStore;
+use tower_sessions::cookie::Cookie;
+use tower_sessions::cookie::CookieJar;
+pub use tower_sessions::cookie::Key;
+use tower_sessions::cookie::time::Duration;
+use tower_sessions::cookie::time::OffsetDateTime;
+use tower_sessions::session::Id;
+use tower_sessions::session::Record;
+use tracing::debug;
+use tracing::error;
+
+use crate::session::SessionSetup;
+
+/// Keys used by the cookie session store
+///
+/// Cookies are encrypted and authenticated using `key`.
+/// Cookies encrypted with one of the `previous_keys` are still accepted
+/// and will be re-encrypted using `key` on their next response.
+/// This allows rotating the key without logging out every user.
+#[derive(Clone)]
+pub struct CookieStoreSetup {
+    /// The key new cookies are encrypted with
+    pub key: Key,
+
+    /// Previous keys which are still accepted to decrypt cookies
+    pub previous_keys: Vec<Key>,
+}
+
+impl CookieStoreSetup {
+    /// Constructs a new `CookieStoreSetup` without any previous keys
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            previous_keys: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Debug for CookieStoreSetup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieStoreSetup")
+            .field("previous_keys", &self.previous_keys.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Layer storing the entire session in an encrypted cookie
+///
+/// It is used in place of tower-session's `SessionManagerLayer`
+/// when the [`SessionStoreSetup::Cookie`](crate::session::SessionStoreSetup::Cookie) is selected.
+///
+/// Since browsers limit a cookie's size to about 4kb,
+/// this store is only suitable for sessions with very little data.
+#[derive(Debug, Clone)]
+pub struct CookieSessionLayer {
+    setup: Arc<SessionSetup>,
+    keys: Arc<CookieStoreSetup>,
+}
+
+impl CookieSessionLayer {
+    /// Constructs a new `CookieSessionLayer`
+    pub fn new(setup: SessionSetup, keys: CookieStoreSetup) -> Self {
+        Self {
+            setup: Arc::new(setup),
+            keys: Arc::new(keys),
+        }
+    }
+
+    /// Reads and decrypts the session cookie from a request's headers
+    fn read_cookie(&self, headers: &HeaderMap) -> Option<Record> {
+        let mut jar = CookieJar::new();
+        for value in headers.get_all(header::COOKIE) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for cookie in Cookie::split_parse(value).flatten() {
+                jar.add_original(cookie.into_owned());
+            }
+        }
+
+        let cookie = std::iter::once(&self.keys.key)
+            .chain(&self.keys.previous_keys)
+            .find_map(|key| jar.private(key).get(&self.setup.cookie_name))?;
+
+        let record: Record = serde_json::from_str(cookie.value())
+            .inspect_err(|error| debug!(error.display = %error, "Malformed session cookie"))
+            .ok()?;
+        (record.expiry_date > OffsetDateTime::now_utc()).then_some(record)
+    }
+
+    /// Encrypts a record into a `Set-Cookie` header value
+    ///
+    /// If `record` is `None`, the returned header removes the cookie.
+    fn write_cookie(&self, record: Option<&Record>) -> Option<HeaderValue> {
+        let setup = &*self.setup;
+
+        let value = match record {
+            Some(record) => serde_json::to_string(record)
+                .inspect_err(|error| error!(error.display = %error, "Failed to serialize session"))
+                .ok()?,
+            None => String::new(),
+        };
+
+        let mut cookie = Cookie::build((setup.cookie_name.clone(), value))
+            .path(setup.cookie_path.clone())
+            .secure(setup.cookie_secure)
+            .http_only(setup.cookie_http_only)
+            .same_site(setup.cookie_same_site);
+        if let Some(domain) = &setup.cookie_domain {
+            cookie = cookie.domain(domain.clone());
+        }
+
+        let cookie = match record {
+            Some(record) => {
+                if !matches!(setup.expiry, Expiry::OnSessionEnd) {
+                    cookie = cookie.expires(record.expiry_date);
+                }
+                let mut jar = CookieJar::new();
+                jar.private_mut(&self.keys.key).add(cookie.build());
+                jar.get(&setup.cookie_name)?.clone()
+            }
+            None => cookie.max_age(Duration::ZERO).build(),
+        };
+        HeaderValue::try_from(cookie.to_string()).ok()
+    }
+}
+
+impl<S> Layer<S> for CookieSessionLayer {
+    type Service = CookieSessionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CookieSessionService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service produced by the [`CookieSessionLayer`]
+#[derive(Debug, Clone)]
+pub struct CookieSessionService<S> {
+    inner: S,
+    layer: CookieSessionLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CookieSessionService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        // The service which has been polled ready is kept, the fresh clone is left in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            let record = layer.read_cookie(req.headers());
+            let had_cookie = record.is_some();
+            let id = record.as_ref().map(|record| record.id);
+            let store = Arc::new(RequestRecordStore(Mutex::new(record)));
+            let session = Session::new(id, store.clone(), Some(layer.setup.expiry));
+
+            req.extensions_mut().insert(session.clone());
+            let mut response = inner.call(req).await?;
+
+            let set_cookie = if session.is_empty().await {
+                if had_cookie && session.is_modified() {
+                    layer.write_cookie(None)
+                } else {
+                    None
+                }
+            } else {
+                if let Err(error) = session.save().await {
+                    error!(
+                        error.display = %error,
+                        error.debug = ?error,
+                        "Failed to save session"
+                    );
+                    let mut response = Response::default();
+                    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                    return Ok(response);
+                }
+                layer.write_cookie(store.get().as_ref())
+            };
+
+            if let Some(set_cookie) = set_cookie {
+                response
+                    .headers_mut()
+                    .append(header::SET_COOKIE, set_cookie);
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// A [`SessionStore`] holding the single session of a single request
+///
+/// It is used to feed tower-session's [`Session`] from and into the session cookie.
+#[derive(Debug)]
+struct RequestRecordStore(Mutex<Option<Record>>);
+
+impl RequestRecordStore {
+    fn get(&self) -> Option<Record> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn set(&self, record: Option<Record>) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = record;
+    }
+}
+
+#[async_trait]
+impl SessionStore for RequestRecordStore {
+    async fn create(
+        &self,
+        session_record: &mut Record,
+    ) -> tower_sessions::session_store::Result<()> {
+        self.set(Some(session_record.clone()));
+        Ok(())
+    }
+
+    async fn save(&self, session_record: &Record) -> tower_sessions::session_store::Result<()> {
+        self.set(Some(session_record.clone()));
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> tower_sessions::session_store::Result<Option<Record>> {
+        Ok(self.get().filter(|record| record.id == *session_id))
+    }
+
+    async fn delete(&self, session_id: &Id) -> tower_sessions::session_store::Result<()> {
+        let mut guard = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if guard
+            .as_ref()
+            .is_some_and(|record| record.id == *session_id)
+        {
+            *guard = None;
+        }
+        Ok(())
+    }
+}
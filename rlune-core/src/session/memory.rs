@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use async_trait::async_trait;
+use tower_sessions::ExpiredDeletion;
+use tower_sessions::SessionStore;
+use tower_sessions::cookie::time::OffsetDateTime;
+use tower_sessions::session::Id;
+use tower_sessions::session::Record;
+
+/// A session store keeping all sessions in memory
+///
+/// It is intended for tests and small deployments without a database.
+/// All sessions are lost when the process exits.
+///
+/// Cloning a `MemoryStore` is cheap and the clone shares its sessions with the original.
+/// This can be used to share sessions between several test servers running in the same process.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    sessions: Arc<Mutex<HashMap<Id, Record>>>,
+}
+
+impl MemoryStore {
+    /// Constructs a new empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of stored sessions including expired ones
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns whether the store contains no sessions
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Id, Record>> {
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn create(
+        &self,
+        session_record: &mut Record,
+    ) -> tower_sessions::session_store::Result<()> {
+        let mut sessions = self.lock();
+        while sessions.contains_key(&session_record.id) {
+            session_record.id = Id::default();
+        }
+        sessions.insert(session_record.id, session_record.clone());
+        Ok(())
+    }
+
+    async fn save(&self, session_record: &Record) -> tower_sessions::session_store::Result<()> {
+        self.lock()
+            .insert(session_record.id, session_record.clone());
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> tower_sessions::session_store::Result<Option<Record>> {
+        let now = OffsetDateTime::now_utc();
+        Ok(self
+            .lock()
+            .get(session_id)
+            .filter(|record| record.expiry_date > now)
+            .cloned())
+    }
+
+    async fn delete(&self, session_id: &Id) -> tower_sessions::session_store::Result<()> {
+        self.lock().remove(session_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for MemoryStore {
+    async fn delete_expired(&self) -> tower_sessions::session_store::Result<()> {
+        let now = OffsetDateTime::now_utc();
+        self.lock().retain(|_, record| record.expiry_date > now);
+        Ok(())
+    }
+}
@@ -0,0 +1,561 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rorm::Database;
+use rorm::Model;
+use rorm::and;
+#[cfg(feature = "rorm-postgres-only")]
+use rorm::db::Executor;
+#[cfg(feature = "rorm-postgres-only")]
+use rorm::db::executor::Nothing;
+#[cfg(feature = "rorm-postgres-only")]
+use rorm::db::sql::value::Value as SqlValue;
+use rorm::fields::types::Json;
+use schemars::_serde_json::Value;
+use thiserror::Error;
+use tower::util::Either;
+use tower_sessions::ExpiredDeletion;
+pub use tower_sessions::Expiry;
+pub use tower_sessions::Session;
+use tower_sessions::SessionManagerLayer;
+use tower_sessions::SessionStore;
+pub use tower_sessions::cookie::SameSite;
+use tower_sessions::cookie::time::Duration;
+use tower_sessions::cookie::time::OffsetDateTime;
+pub use tower_sessions::session::Error;
+use tower_sessions::session::Id;
+use tower_sessions::session::Record;
+use tower_sessions::session_store::Error as StoreError;
+use tracing::debug;
+use tracing::instrument;
+
+pub use self::cookie::CookieSessionLayer;
+pub use self::cookie::CookieStoreSetup;
+pub use self::cookie::Key;
+pub use self::memory::MemoryStore;
+pub use self::throttle::ThrottledStore;
+use crate::Module;
+use crate::TryGlobalError;
+use crate::metrics::Metrics;
+use crate::module::registry::Registry;
+use crate::module::registry::tasks::RestartPolicy;
+
+mod cookie;
+mod memory;
+mod throttle;
+
+/// Declares how sessions should be handled
+///
+/// The default mirrors the behaviour of previous versions:
+/// sessions are stored in the database using the [`RormStore`]
+/// and expire after 24h of inactivity.
+#[derive(Debug, Clone)]
+pub struct SessionSetup {
+    /// Whether sessions are enabled at all
+    ///
+    /// If disabled, handlers using the [`Session`] extractor will fail.
+    pub enabled: bool,
+
+    /// The name of the cookie storing the session id
+    pub cookie_name: Cow<'static, str>,
+
+    /// The cookie's `Domain` attribute
+    pub cookie_domain: Option<Cow<'static, str>>,
+
+    /// The cookie's `Path` attribute
+    pub cookie_path: Cow<'static, str>,
+
+    /// Whether the cookie should have the `Secure` attribute
+    pub cookie_secure: bool,
+
+    /// Whether the cookie should have the `HttpOnly` attribute
+    pub cookie_http_only: bool,
+
+    /// The cookie's `SameSite` attribute
+    pub cookie_same_site: SameSite,
+
+    /// When a session expires
+    ///
+    /// Use [`Expiry::OnInactivity`] for an expiry which is extended with every request
+    /// and [`Expiry::AtDateTime`] for an absolute one.
+    pub expiry: Expiry,
+
+    /// Where sessions are stored
+    pub store: SessionStoreSetup,
+
+    /// How often expired sessions should be deleted from the store
+    ///
+    /// Set to `None` to disable the periodic cleanup.
+    /// This has no effect on the cookie store.
+    pub cleanup_interval: Option<Duration>,
+
+    /// The minimum amount an unchanged session's expiry has to move before it is written
+    ///
+    /// Without this threshold, every request would write its session back to the store
+    /// just to extend its expiry.
+    /// See [`ThrottledStore`] for details.
+    pub touch_threshold: Duration,
+}
+
+impl Default for SessionSetup {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cookie_name: Cow::Borrowed("id"),
+            cookie_domain: None,
+            cookie_path: Cow::Borrowed("/"),
+            cookie_secure: true,
+            cookie_http_only: true,
+            cookie_same_site: SameSite::Lax,
+            expiry: Expiry::OnInactivity(Duration::hours(24)),
+            store: SessionStoreSetup::default(),
+            cleanup_interval: Some(Duration::hours(1)),
+            touch_threshold: Duration::minutes(1),
+        }
+    }
+}
+
+impl SessionSetup {
+    /// Constructs a `SessionSetup` which disables sessions entirely
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+}
+
+/// Declares where sessions should be stored
+#[derive(Debug, Clone, Default)]
+pub enum SessionStoreSetup {
+    /// Store sessions in the database using the [`RormStore`]
+    ///
+    /// This requires the [`Database`] module to be registered.
+    #[default]
+    Database,
+
+    /// Store sessions in memory using the [`MemoryStore`]
+    ///
+    /// The store is shared with every clone of the `MemoryStore`.
+    Memory(MemoryStore),
+
+    /// Store sessions in an encrypted cookie
+    ///
+    /// This doesn't require any server side storage
+    /// but limits the amount of data a session can hold to about 4kb.
+    Cookie(CookieStoreSetup),
+
+    /// Store sessions in a custom [`RluneSessionStore`]
+    Custom(SharedSessionStore),
+}
+
+/// A [`SessionStore`] which is able to delete its expired sessions
+///
+/// This trait is implemented for every [`ExpiredDeletion`]
+/// and only exists to be object safe.
+#[async_trait]
+pub trait RluneSessionStore: SessionStore {
+    /// Deletes all expired sessions
+    async fn delete_expired(&self) -> tower_sessions::session_store::Result<()>;
+}
+
+#[async_trait]
+impl<T: ExpiredDeletion> RluneSessionStore for T {
+    async fn delete_expired(&self) -> tower_sessions::session_store::Result<()> {
+        ExpiredDeletion::delete_expired(self).await
+    }
+}
+
+/// A type erased [`RluneSessionStore`] which can be cloned cheaply
+#[derive(Debug, Clone)]
+pub struct SharedSessionStore(Arc<dyn RluneSessionStore>);
+
+impl SharedSessionStore {
+    /// Wraps a store
+    pub fn new(store: impl RluneSessionStore) -> Self {
+        Self(Arc::new(store))
+    }
+}
+
+#[async_trait]
+impl SessionStore for SharedSessionStore {
+    async fn create(
+        &self,
+        session_record: &mut Record,
+    ) -> tower_sessions::session_store::Result<()> {
+        self.0.create(session_record).await
+    }
+
+    async fn save(&self, session_record: &Record) -> tower_sessions::session_store::Result<()> {
+        self.0.save(session_record).await
+    }
+
+    async fn load(&self, session_id: &Id) -> tower_sessions::session_store::Result<Option<Record>> {
+        self.0.load(session_id).await
+    }
+
+    async fn delete(&self, session_id: &Id) -> tower_sessions::session_store::Result<()> {
+        self.0.delete(session_id).await
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for SharedSessionStore {
+    async fn delete_expired(&self) -> tower_sessions::session_store::Result<()> {
+        self.0.delete_expired().await
+    }
+}
+
+/// The layer managing sessions
+///
+/// It is either tower-session's `SessionManagerLayer` using a server side store
+/// or the [`CookieSessionLayer`].
+pub type SessionLayer = Either<SessionManagerLayer<ThrottledStore>, CookieSessionLayer>;
+
+/// The session handling constructed from a [`SessionSetup`]
+#[derive(Debug, Clone)]
+pub struct Sessions {
+    /// The layer managing sessions
+    pub layer: SessionLayer,
+
+    /// The server side store used by the layer
+    ///
+    /// This is `None` for the cookie store.
+    pub store: Option<ThrottledStore>,
+}
+
+/// Constructs the session handling according to a [`SessionSetup`]
+///
+/// Returns `None` if sessions are disabled.
+///
+/// # Errors
+/// If the setup requires a module which has not been registered
+pub fn build(setup: &SessionSetup) -> Result<Option<Sessions>, TryGlobalError> {
+    if !setup.enabled {
+        return Ok(None);
+    }
+
+    let store = match &setup.store {
+        SessionStoreSetup::Database => {
+            SharedSessionStore::new(RormStore::new(Database::try_global()?.clone()))
+        }
+        SessionStoreSetup::Memory(store) => SharedSessionStore::new(store.clone()),
+        SessionStoreSetup::Cookie(keys) => {
+            return Ok(Some(Sessions {
+                layer: Either::Right(CookieSessionLayer::new(setup.clone(), keys.clone())),
+                store: None,
+            }));
+        }
+        SessionStoreSetup::Custom(store) => store.clone(),
+    };
+    let store = ThrottledStore::new(store, setup.touch_threshold);
+
+    let mut layer = SessionManagerLayer::new(store.clone())
+        .with_name(setup.cookie_name.clone())
+        .with_path(setup.cookie_path.clone())
+        .with_secure(setup.cookie_secure)
+        .with_http_only(setup.cookie_http_only)
+        .with_same_site(setup.cookie_same_site)
+        .with_expiry(setup.expiry)
+        .with_always_save(true);
+    if let Some(domain) = &setup.cookie_domain {
+        layer = layer.with_domain(domain.clone());
+    }
+    Ok(Some(Sessions {
+        layer: Either::Left(layer),
+        store: Some(store),
+    }))
+}
+
+/// Spawns a supervised task deleting expired sessions from a store every `period`
+///
+/// Failures are logged and retried in the next period.
+/// The task is cancelled when the modules shut down.
+pub fn spawn_cleanup(store: impl ExpiredDeletion + Clone, period: Duration) {
+    Registry::global().tasks().spawn_periodic(
+        "session-cleanup",
+        period.unsigned_abs().max(std::time::Duration::from_secs(1)),
+        RestartPolicy::OnPanic,
+        move || {
+            let store = store.clone();
+            async move {
+                debug!("Deleting expired sessions");
+                store.delete_expired().await?;
+                Ok(())
+            }
+        },
+    );
+}
+
+/// Registers the `rlune_sessions` gauge reporting the number of stored sessions
+///
+/// The database store's unexpired sessions are counted every minute by a supervised task
+/// while the memory store is counted whenever the metrics are rendered.
+/// Sessions in cookies or custom stores are not counted.
+///
+/// # Errors
+/// If the setup requires a module which has not been registered
+pub fn register_metrics(setup: &SessionSetup) -> Result<(), TryGlobalError> {
+    const NAME: &str = "rlune_sessions";
+    const HELP: &str = "Number of sessions in the session store";
+
+    if !setup.enabled {
+        return Ok(());
+    }
+    match &setup.store {
+        SessionStoreSetup::Database => {
+            let db = Database::try_global()?.clone();
+            let gauge = Metrics::global().gauge(NAME, HELP, &[]);
+            Registry::global().tasks().spawn_periodic(
+                "session-metrics",
+                std::time::Duration::from_secs(60),
+                RestartPolicy::OnPanic,
+                move || {
+                    let db = db.clone();
+                    let gauge = gauge.clone();
+                    async move {
+                        let count = rorm::query(&db, RluneSession.id.count())
+                            .condition(
+                                RluneSession
+                                    .expires_at
+                                    .greater_than(OffsetDateTime::now_utc()),
+                            )
+                            .one()
+                            .await?;
+                        gauge.set(count);
+                        Ok(())
+                    }
+                },
+            );
+        }
+        SessionStoreSetup::Memory(store) => {
+            let store = store.clone();
+            Metrics::global().gauge_fn(NAME, HELP, &[], move || {
+                i64::try_from(store.len()).unwrap_or(i64::MAX)
+            });
+        }
+        SessionStoreSetup::Cookie(_) | SessionStoreSetup::Custom(_) => {}
+    }
+    Ok(())
+}
+
+#[derive(Model)]
+pub struct RluneSession {
+    #[rorm(primary_key, max_length = 255)]
+    id: String,
+    expires_at: OffsetDateTime,
+    data: Json<HashMap<String, Value>>,
+}
+
+/// The session store for rorm
+pub struct RormStore {
+    db: Database,
+}
+
+impl RormStore {
+    /// Construct a new Store
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+}
+
+impl Debug for RormStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rlune rorm store")
+    }
+}
+
+impl Clone for RormStore {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for RormStore {
+    #[instrument(level = "trace")]
+    async fn create(
+        &self,
+        session_record: &mut Record,
+    ) -> tower_sessions::session_store::Result<()> {
+        debug!("Creating new session");
+        let mut tx = self
+            .db
+            .start_transaction()
+            .await
+            .map_err(RormStoreError::from)?;
+        loop {
+            let existing = rorm::query(&mut tx, RluneSession)
+                .condition(RluneSession.id.equals(session_record.id.to_string()))
+                .optional()
+                .await
+                .map_err(RormStoreError::from)?;
+
+            if existing.is_none() {
+                rorm::insert(&mut tx, RluneSession)
+                    .return_nothing()
+                    .single(&RluneSession {
+                        id: session_record.id.to_string(),
+                        expires_at: session_record.expiry_date,
+                        data: Json(session_record.data.clone()),
+                    })
+                    .await
+                    .map_err(RormStoreError::from)?;
+
+                break;
+            }
+
+            session_record.id = Id::default();
+        }
+
+        tx.commit().await.map_err(RormStoreError::from)?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace")]
+    async fn save(&self, session_record: &Record) -> tower_sessions::session_store::Result<()> {
+        let Record {
+            id,
+            data,
+            expiry_date,
+        } = session_record;
+
+        // rorm doesn't provide upserts, so it is written by hand if postgres is the only driver.
+        #[cfg(feature = "rorm-postgres-only")]
+        {
+            let id = id.to_string();
+            let data = serde_json::to_vec(data).map_err(RormStoreError::from)?;
+            (&self.db)
+                .execute::<Nothing>(
+                    format!(
+                        r#"INSERT INTO "{table}" ("id", "expires_at", "data") VALUES ($1, $2, $3) ON CONFLICT ("id") DO UPDATE SET "expires_at" = EXCLUDED."expires_at", "data" = EXCLUDED."data";"#,
+                        table = RluneSession::TABLE,
+                    ),
+                    vec![
+                        SqlValue::String(&id),
+                        SqlValue::TimeOffsetDateTime(*expiry_date),
+                        SqlValue::Binary(&data),
+                    ],
+                )
+                .await
+                .map_err(RormStoreError::from)?;
+        }
+
+        #[cfg(not(feature = "rorm-postgres-only"))]
+        {
+            let mut tx = self
+                .db
+                .start_transaction()
+                .await
+                .map_err(RormStoreError::from)?;
+
+            let existing_session = rorm::query(&mut tx, RluneSession)
+                .condition(RluneSession.id.equals(id.to_string()))
+                .optional()
+                .await
+                .map_err(RormStoreError::from)?;
+
+            if existing_session.is_some() {
+                rorm::update(&mut tx, RluneSession)
+                    .set(RluneSession.expires_at, *expiry_date)
+                    .set(RluneSession.data, Json(data.clone()))
+                    .condition(RluneSession.id.equals(id.to_string()))
+                    .await
+                    .map_err(RormStoreError::from)?;
+            } else {
+                rorm::insert(&mut tx, RluneSession)
+                    .return_nothing()
+                    .single(&RluneSession {
+                        id: id.to_string(),
+                        expires_at: *expiry_date,
+                        data: Json(data.clone()),
+                    })
+                    .await
+                    .map_err(RormStoreError::from)?;
+            }
+
+            tx.commit().await.map_err(RormStoreError::from)?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace")]
+    async fn load(&self, session_id: &Id) -> tower_sessions::session_store::Result<Option<Record>> {
+        debug!("Loading session");
+        let db = &self.db;
+
+        let session = rorm::query(db, RluneSession)
+            .condition(and!(
+                RluneSession.id.equals(session_id.to_string()),
+                RluneSession
+                    .expires_at
+                    .greater_than(OffsetDateTime::now_utc())
+            ))
+            .optional()
+            .await
+            .map_err(RormStoreError::from)?;
+
+        Ok(match session {
+            None => None,
+            Some(session) => Some(Record {
+                id: Id::from_str(session.id.as_str()).map_err(RormStoreError::from)?,
+                data: session.data.into_inner(),
+                expiry_date: session.expires_at,
+            }),
+        })
+    }
+
+    #[instrument(level = "trace")]
+    async fn delete(&self, session_id: &Id) -> tower_sessions::session_store::Result<()> {
+        let db = &self.db;
+
+        rorm::delete(db, RluneSession)
+            .condition(RluneSession.id.equals(session_id.to_string()))
+            .await
+            .map_err(RormStoreError::from)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for RormStore {
+    #[instrument(level = "trace")]
+    async fn delete_expired(&self) -> tower_sessions::session_store::Result<()> {
+        let db = &self.db;
+
+        rorm::delete(db, RluneSession)
+            .condition(RluneSession.expires_at.less_than(OffsetDateTime::now_utc()))
+            .await
+            .map_err(RormStoreError::from)?;
+
+        Ok(())
+    }
+}
+
+/// Error type that is used in the [SessionStore] trait
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum RormStoreError {
+    #[error("Database error: {0}")]
+    Database(#[from] rorm::Error),
+    #[error("Decoding of id failed: {0}")]
+    DecodingFailed(#[from] base64::DecodeSliceError),
+    #[error("Encoding of data failed: {0}")]
+    EncodingFailed(#[from] serde_json::Error),
+}
+
+impl From<RormStoreError> for StoreError {
+    fn from(value: RormStoreError) -> Self {
+        Self::Backend(value.to_string())
+    }
+}
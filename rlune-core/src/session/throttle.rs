@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use async_trait::async_trait;
+use tower_sessions::ExpiredDeletion;
+use tower_sessions::SessionStore;
+use tower_sessions::cookie::time::Duration;
+use tower_sessions::cookie::time::OffsetDateTime;
+use tower_sessions::session::Id;
+use tower_sessions::session::Record;
+
+use crate::session::SharedSessionStore;
+
+/// A session store wrapper skipping writes which would only "touch" a session
+///
+/// Sessions are saved on every request to extend their expiry.
+/// This wrapper remembers the records it loaded and skips saving a record
+/// if its data is unchanged and its expiry moved by less than `threshold`.
+///
+/// As a consequence, a session may expire up to `threshold` earlier than configured.
+#[derive(Debug, Clone)]
+pub struct ThrottledStore {
+    inner: SharedSessionStore,
+    threshold: Duration,
+    loaded: Arc<Mutex<HashMap<Id, Record>>>,
+}
+
+impl ThrottledStore {
+    /// Wraps a store
+    ///
+    /// A `threshold` of zero disables the throttling.
+    pub fn new(inner: SharedSessionStore, threshold: Duration) -> Self {
+        Self {
+            inner,
+            threshold,
+            loaded: Default::default(),
+        }
+    }
+
+    /// Removes a record from the loaded ones and checks whether `record` has to be written
+    fn needs_write(&self, record: &Record) -> bool {
+        let loaded = self
+            .loaded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&record.id);
+        match loaded {
+            Some(loaded) => {
+                loaded.data != record.data
+                    || (record.expiry_date - loaded.expiry_date).abs() >= self.threshold
+            }
+            None => true,
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for ThrottledStore {
+    async fn create(
+        &self,
+        session_record: &mut Record,
+    ) -> tower_sessions::session_store::Result<()> {
+        self.inner.create(session_record).await
+    }
+
+    async fn save(&self, session_record: &Record) -> tower_sessions::session_store::Result<()> {
+        if self.needs_write(session_record) {
+            self.inner.save(session_record).await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn load(&self, session_id: &Id) -> tower_sessions::session_store::Result<Option<Record>> {
+        let record = self.inner.load(session_id).await?;
+        if let Some(record) = &record
+            && self.threshold.is_positive()
+        {
+            self.loaded
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(record.id, record.clone());
+        }
+        Ok(record)
+    }
+
+    async fn delete(&self, session_id: &Id) -> tower_sessions::session_store::Result<()> {
+        self.loaded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(session_id);
+        self.inner.delete(session_id).await
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for ThrottledStore {
+    async fn delete_expired(&self) -> tower_sessions::session_store::Result<()> {
+        let now = OffsetDateTime::now_utc();
+        self.loaded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|_, record| record.expiry_date > now);
+        ExpiredDeletion::delete_expired(&self.inner).await
+    }
+}
@@ -16,8 +16,10 @@ use tracing::error;
 
 use crate::handler::response_body::ResponseBody;
 use crate::handler::response_body::ShouldBeResponseBody;
+use crate::metrics::Metrics;
 use crate::schema_generator::SchemaGenerator;
 use crate::stuff::api_json::ApiJson;
+use crate::stuff::request_id::RequestId;
 use crate::stuff::schema::ApiErrorResponse;
 use crate::stuff::schema::ApiStatusCode;
 
@@ -145,6 +147,13 @@ impl ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         self.emit_tracing_event();
+        Metrics::global()
+            .counter(
+                "rlune_api_errors_total",
+                "Number of error responses by their api status code",
+                &[("code", &format!("{:?}", self.code))],
+            )
+            .inc();
 
         let res = (
             if (self.code as u16) < 2000 {
@@ -162,6 +171,7 @@ impl IntoResponse for ApiError {
                     ApiStatusCode::InternalServerError => "Internal server error",
                 }
                 .to_string(),
+                request_id: RequestId::current().map(|id| id.to_string()),
             }),
         );
 
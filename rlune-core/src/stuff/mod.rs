@@ -4,5 +4,7 @@
 pub mod api_error;
 pub mod api_json;
 pub mod env;
+pub mod request_id;
 pub mod schema;
 pub mod swap_lock;
+pub mod trace_context;
@@ -0,0 +1,88 @@
+//! Identifier of the request currently being handled
+//!
+//! It is assigned by rlune's request tracing (or taken from the client's `X-Request-Id` header),
+//! attached to the request's tracing span and included in error responses,
+//! so users can quote it when reporting a problem.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+use axum::extract::FromRequestParts;
+use axum::http::HeaderName;
+use axum::http::HeaderValue;
+use axum::http::request::Parts;
+use uuid::Uuid;
+
+use crate::stuff::api_error::ApiError;
+
+/// The header a request id is read from and returned in
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The maximum length of a request id accepted from a client
+const MAX_LENGTH: usize = 128;
+
+tokio::task_local! {
+    /// The id of the request handled by the current task
+    static CURRENT: RequestId;
+}
+
+/// Identifier of a request
+///
+/// It can be taken as handler argument and is available in the request's extensions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(Arc<str>);
+
+impl RequestId {
+    /// Generates a new random request id
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().simple().to_string().into())
+    }
+
+    /// Uses the value of a client provided header as request id
+    ///
+    /// Returns `None` if the value is empty, too long or contains anything but visible ascii characters.
+    pub fn from_header(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?;
+        (!value.is_empty()
+            && value.len() <= MAX_LENGTH
+            && value.bytes().all(|byte| byte.is_ascii_graphic()))
+        .then(|| Self(value.into()))
+    }
+
+    /// Gets the id as string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Gets the id of the request handled by the current task
+    ///
+    /// Returns `None` outside of [`RequestId::scope`] i.e. if request tracing is disabled
+    /// or the caller runs in a task spawned by the handler.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Runs a future with `self` as [current](RequestId::current) request id
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<S: Sync> FromRequestParts<S> for RequestId {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Self>()
+            .cloned()
+            .ok_or_else(|| ApiError::server_error("Request tracing is disabled"))
+    }
+}
@@ -44,6 +44,10 @@ pub struct ApiErrorResponse {
     ///
     /// May be used for displaying purposes
     pub message: String,
+    /// The id of the request which failed
+    ///
+    /// Users may quote it when reporting the error.
+    pub request_id: Option<String>,
 }
 
 /// A type without any runtime value
@@ -0,0 +1,68 @@
+//! Propagation of the W3C trace context across process boundaries
+//!
+//! With the `opentelemetry` feature, rlune continues the trace of an incoming request's
+//! `traceparent` header and outgoing requests should carry the current span's context
+//! using [`inject`].
+//!
+//! Without the feature, these functions do nothing.
+//! Crates like rlune's contrib modules can therefore call them unconditionally
+//! and the propagation starts to work once the application enables the feature.
+
+use axum::http::HeaderMap;
+#[cfg(feature = "opentelemetry")]
+use opentelemetry::global;
+#[cfg(feature = "opentelemetry")]
+use opentelemetry::propagation::Extractor;
+#[cfg(feature = "opentelemetry")]
+use opentelemetry::propagation::Injector;
+use tracing::Span;
+#[cfg(feature = "opentelemetry")]
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Adds the headers propagating the current span's trace context to an outgoing request
+///
+/// `insert` is called with each header's name and value.
+/// It is a closure instead of a [`HeaderMap`] to support http clients using another version of `http`.
+pub fn inject(mut insert: impl FnMut(&str, String)) {
+    #[cfg(feature = "opentelemetry")]
+    {
+        /// Adapter from a closure to opentelemetry's [`Injector`]
+        struct Insert<F>(F);
+        impl<F: FnMut(&str, String)> Injector for Insert<F> {
+            fn set(&mut self, key: &str, value: String) {
+                (self.0)(key, value)
+            }
+        }
+
+        let context = Span::current().context();
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut Insert(&mut insert))
+        });
+    }
+    #[cfg(not(feature = "opentelemetry"))]
+    let _ = &mut insert;
+}
+
+/// Continues the trace context of an incoming request in `span`
+pub fn extract(span: &Span, headers: &HeaderMap) {
+    #[cfg(feature = "opentelemetry")]
+    {
+        /// Adapter from a [`HeaderMap`] to opentelemetry's [`Extractor`]
+        struct Headers<'a>(&'a HeaderMap);
+        impl Extractor for Headers<'_> {
+            fn get(&self, key: &str) -> Option<&str> {
+                self.0.get(key)?.to_str().ok()
+            }
+
+            fn keys(&self) -> Vec<&str> {
+                self.0.keys().map(|name| name.as_str()).collect()
+            }
+        }
+
+        let context =
+            global::get_text_map_propagator(|propagator| propagator.extract(&Headers(headers)));
+        span.set_parent(context);
+    }
+    #[cfg(not(feature = "opentelemetry"))]
+    let _ = (span, headers);
+}
@@ -0,0 +1,186 @@
+//! Cors related [`RouteMetadata`]
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use rlune_core::router::RouteMetadata;
+
+/// Cors related [`RouteMetadata`]
+///
+/// It declares which origins may read a route's responses.
+#[derive(Debug, Clone, Default)]
+pub struct CorsMetadata {
+    /// Origins which may read the responses
+    pub allowed_origins: Vec<CorsOrigin>,
+
+    /// Whether requests may include credentials like cookies
+    ///
+    /// Credentials are only allowed for origins matching an [`Exact`](CorsOrigin::Exact)
+    /// or [`Pattern`](CorsOrigin::Pattern) origin, never for ones only matching [`Any`](CorsOrigin::Any).
+    pub allow_credentials: bool,
+
+    /// Response headers (besides the CORS-safelisted ones) the frontend may read
+    pub exposed_headers: Vec<Cow<'static, str>>,
+
+    /// How long browsers may cache the answer to a preflight request
+    ///
+    /// Browsers use a short default (5 seconds in chromium) if this is `None`.
+    pub max_age: Option<Duration>,
+}
+
+impl CorsMetadata {
+    /// Checks whether `origin` may read the responses
+    pub fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed.matches(origin))
+    }
+
+    /// Checks whether `origin` may include credentials in its requests
+    pub(crate) fn allows_credentials(&self, origin: &str) -> bool {
+        self.allow_credentials
+            && self
+                .allowed_origins
+                .iter()
+                .any(|allowed| *allowed != CorsOrigin::Any && allowed.matches(origin))
+    }
+}
+
+impl RouteMetadata for CorsMetadata {
+    fn merge(&mut self, other: &Self) {
+        for origin in &other.allowed_origins {
+            if !self.allowed_origins.contains(origin) {
+                self.allowed_origins.push(origin.clone());
+            }
+        }
+        self.allow_credentials |= other.allow_credentials;
+        for header in &other.exposed_headers {
+            if !self.exposed_headers.contains(header) {
+                self.exposed_headers.push(header.clone());
+            }
+        }
+        self.max_age = self.max_age.max(other.max_age);
+    }
+}
+
+/// An origin (or a set of origins) allowed to read responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsOrigin {
+    /// Any origin
+    ///
+    /// Responses are shared using the `*` wildcard which browsers don't accept for credentialed requests,
+    /// so otherwise any website could act on behalf of the session's user.
+    Any,
+
+    /// A single origin like `https://example.com`
+    Exact(Cow<'static, str>),
+
+    /// Origins matching a pattern like `https://*.example.com`
+    ///
+    /// A `*` matches a non-empty sequence of characters except `/` and `:`,
+    /// so it can't be used to match a different scheme or port.
+    Pattern(Cow<'static, str>),
+}
+
+impl CorsOrigin {
+    /// Constructs a [`CorsOrigin::Exact`]
+    pub fn exact(origin: impl Into<Cow<'static, str>>) -> Self {
+        Self::Exact(origin.into())
+    }
+
+    /// Constructs a [`CorsOrigin::Pattern`]
+    pub fn pattern(pattern: impl Into<Cow<'static, str>>) -> Self {
+        Self::Pattern(pattern.into())
+    }
+
+    /// Checks whether `origin` is matched by `self`
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            CorsOrigin::Any => true,
+            CorsOrigin::Exact(exact) => exact.eq_ignore_ascii_case(origin),
+            CorsOrigin::Pattern(pattern) => {
+                matches_pattern(&pattern.to_ascii_lowercase(), &origin.to_ascii_lowercase())
+            }
+        }
+    }
+}
+
+/// Matches `origin` against a pattern whose `*` match one or more characters except `/` and `:`
+fn matches_pattern(pattern: &str, origin: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == origin,
+        Some((prefix, rest)) => {
+            let Some(origin) = origin.strip_prefix(prefix) else {
+                return false;
+            };
+            // Try every length the wildcard could match, shortest first
+            for (index, char) in origin.char_indices() {
+                if char == '/' || char == ':' {
+                    return false;
+                }
+                if matches_pattern(rest, &origin[index + char.len_utf8()..]) {
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_matches_subdomains() {
+        let origin = CorsOrigin::pattern("https://*.example.com");
+        assert!(origin.matches("https://app.example.com"));
+        assert!(origin.matches("https://a.b.example.com"));
+        assert!(origin.matches("HTTPS://App.Example.com"));
+        assert!(!origin.matches("https://example.com"));
+        assert!(!origin.matches("https://.example.com"));
+        assert!(!origin.matches("https://app.example.com.evil.org"));
+        assert!(!origin.matches("https://evil.org/.example.com"));
+    }
+
+    #[test]
+    fn pattern_wildcard_stops_at_scheme_and_port() {
+        assert!(!CorsOrigin::pattern("*://example.com").matches("https://evil.org:example.com"));
+        assert!(CorsOrigin::pattern("*://example.com").matches("https://example.com"));
+        assert!(!CorsOrigin::pattern("https://example.*").matches("https://example.com:8080"));
+        assert!(CorsOrigin::pattern("http://localhost:*").matches("http://localhost:8080"));
+        assert!(!CorsOrigin::pattern("http://localhost:*").matches("http://localhost:"));
+    }
+
+    #[test]
+    fn pattern_with_multiple_wildcards() {
+        let origin = CorsOrigin::pattern("https://*.*.example.com");
+        assert!(origin.matches("https://a.b.example.com"));
+        assert!(!origin.matches("https://a.example.com"));
+    }
+
+    #[test]
+    fn exact_ignores_case() {
+        let origin = CorsOrigin::exact("https://example.com");
+        assert!(origin.matches("https://EXAMPLE.com"));
+        assert!(!origin.matches("https://app.example.com"));
+    }
+
+    #[test]
+    fn credentials_are_never_allowed_for_any() {
+        let metadata = CorsMetadata {
+            allowed_origins: vec![CorsOrigin::Any, CorsOrigin::exact("https://example.com")],
+            allow_credentials: true,
+            ..Default::default()
+        };
+        assert!(metadata.allows("https://evil.org"));
+        assert!(!metadata.allows_credentials("https://evil.org"));
+        assert!(metadata.allows_credentials("https://example.com"));
+
+        let metadata = CorsMetadata {
+            allow_credentials: false,
+            ..metadata
+        };
+        assert!(!metadata.allows_credentials("https://example.com"));
+    }
+}
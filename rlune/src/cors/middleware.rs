@@ -0,0 +1,161 @@
+//! The middleware adding CORS headers and answering preflight requests
+
+use std::sync::Arc;
+
+use axum::extract::Request;
+use axum::extract::State;
+use axum::http::header;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
+use axum::http::Method;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use rlune_core::router::MatchedRoute;
+use rlune_core::router::PathRoutes;
+
+use crate::cors::metadata::CorsMetadata;
+use crate::cors::metadata::CorsOrigin;
+
+/// Adds CORS headers to responses and answers preflight requests
+///
+/// The headers are configured by the [`CorsMetadata`] of the matched route
+/// falling back to the `global` one.
+/// Requests without an `Origin` header and requests to routes without any metadata pass unchanged.
+pub(crate) async fn cors_middleware(
+    State(global): State<Option<Arc<CorsMetadata>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(origin) = request.headers().get(header::ORIGIN).cloned() else {
+        return next.run(request).await;
+    };
+    let global = global.as_deref();
+
+    if request.method() == Method::OPTIONS {
+        let requested_method = request
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|value| Method::from_bytes(value.as_bytes()).ok());
+        let path_routes = request.extensions().get::<PathRoutes>();
+        if let (Some(requested_method), Some(path_routes)) = (requested_method, path_routes) {
+            if let Some(response) = preflight(
+                &origin,
+                &requested_method,
+                request.headers(),
+                path_routes,
+                global,
+            ) {
+                return response;
+            }
+        }
+    }
+
+    let metadata = route_metadata(request.extensions().get::<MatchedRoute>(), global).cloned();
+    let mut response = next.run(request).await;
+    if let Some(metadata) = metadata {
+        let headers = response.headers_mut();
+        headers.append(header::VARY, HeaderValue::from_static("origin"));
+        if origin.to_str().is_ok_and(|origin| metadata.allows(origin)) {
+            allow_origin(headers, origin, &metadata);
+            if !metadata.exposed_headers.is_empty() {
+                if let Ok(exposed) = HeaderValue::try_from(metadata.exposed_headers.join(", ")) {
+                    headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, exposed);
+                }
+            }
+        }
+    }
+    response
+}
+
+/// Answers a preflight request
+///
+/// Returns `None` if the requested method is not allowed for the origin,
+/// in which case the request is handled like any other.
+fn preflight<'a>(
+    origin: &HeaderValue,
+    requested_method: &Method,
+    request_headers: &HeaderMap,
+    path_routes: &'a PathRoutes,
+    global: Option<&'a CorsMetadata>,
+) -> Option<Response> {
+    let origin_str = origin.to_str().ok()?;
+
+    // axum routes `HEAD` requests to `GET` handlers
+    let route = path_routes.get(requested_method).or_else(|| {
+        (*requested_method == Method::HEAD)
+            .then(|| path_routes.get(&Method::GET))
+            .flatten()
+    })?;
+    let metadata =
+        route_metadata(Some(route), global).filter(|metadata| metadata.allows(origin_str))?;
+
+    let allowed_methods = path_routes
+        .iter()
+        .filter(|(_, route)| {
+            route_metadata(Some(*route), global).is_some_and(|metadata| metadata.allows(origin_str))
+        })
+        .map(|(method, _)| method.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::VARY,
+        HeaderValue::from_static(
+            "origin, access-control-request-method, access-control-request-headers",
+        ),
+    );
+    allow_origin(headers, origin.clone(), metadata);
+    if let Ok(allowed_methods) = HeaderValue::try_from(allowed_methods) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, allowed_methods);
+    }
+    if let Some(requested_headers) = request_headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            requested_headers.clone(),
+        );
+    }
+    if let Some(max_age) = metadata.max_age {
+        headers.insert(
+            header::ACCESS_CONTROL_MAX_AGE,
+            HeaderValue::from(max_age.as_secs()),
+        );
+    }
+    Some(response)
+}
+
+/// Gets the metadata applying to a route
+fn route_metadata<'a>(
+    route: Option<&'a MatchedRoute>,
+    global: Option<&'a CorsMetadata>,
+) -> Option<&'a CorsMetadata> {
+    route
+        .and_then(|route| route.metadata().get::<CorsMetadata>())
+        .or(global)
+}
+
+/// Adds the headers allowing `origin` to read the response
+///
+/// Origins which are only allowed by [`CorsOrigin::Any`] get the `*` wildcard without credentials.
+fn allow_origin(headers: &mut HeaderMap, origin: HeaderValue, metadata: &CorsMetadata) {
+    let credentials = origin
+        .to_str()
+        .is_ok_and(|origin| metadata.allows_credentials(origin));
+    if credentials {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    } else if metadata.allowed_origins.contains(&CorsOrigin::Any) {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        );
+    } else {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    }
+}
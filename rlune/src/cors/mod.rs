@@ -0,0 +1,38 @@
+//! Cross origin resource sharing
+//!
+//! Browsers only allow frontends to read responses from other origins
+//! if the server permits it using CORS headers.
+//!
+//! The permitted origins are configured globally using [`RouterBuilder::cors`](crate::RouterBuilder::cors)
+//! and per [`RluneRouter`](rlune_core::RluneRouter) using [`CorsRouterExt::cors`]:
+//!
+//! ```no_run
+//! # use rlune::core::RluneRouter;
+//! # use rlune::cors::{CorsMetadata, CorsOrigin, CorsRouterExt};
+//! # fn routes(builder: &mut rlune::RouterBuilder, api: RluneRouter) {
+//! builder
+//!     .cors(CorsMetadata {
+//!         allowed_origins: vec![CorsOrigin::exact("https://app.example.com")],
+//!         allow_credentials: true,
+//!         ..Default::default()
+//!     })
+//!     .add_routes(api.cors(CorsMetadata {
+//!         allowed_origins: vec![CorsOrigin::pattern("https://*.preview.example.com")],
+//!         ..Default::default()
+//!     }));
+//! # }
+//! ```
+//!
+//! A route's [`CorsMetadata`] replaces the global one.
+//!
+//! Preflight requests are answered automatically.
+//! They permit every method registered for the requested path
+//! whose route allows the request's origin.
+
+pub use crate::cors::metadata::CorsMetadata;
+pub use crate::cors::metadata::CorsOrigin;
+pub use crate::cors::router_ext::CorsRouterExt;
+
+mod metadata;
+pub(crate) mod middleware;
+mod router_ext;
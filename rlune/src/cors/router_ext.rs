@@ -0,0 +1,25 @@
+//! [`RluneRouter`] extension trait
+
+use rlune_core::RluneRouter;
+
+use crate::cors::metadata::CorsMetadata;
+
+/// Extension trait for [`RluneRouter`]
+///
+/// It provides convenient methods for sharing resources with other origins.
+pub trait CorsRouterExt {
+    /// Configures which origins may access all handlers in this router
+    ///
+    /// This adds the [`CorsMetadata`] to all handlers, replacing the global one
+    /// set using [`RouterBuilder::cors`](crate::RouterBuilder::cors).
+    /// The metadata is added regardless of whether the handlers have been added before or after.
+    /// No middleware is applied to the router itself,
+    /// the [`RouterBuilder`](crate::RouterBuilder) applies the CORS middleware to every route.
+    fn cors(self, metadata: CorsMetadata) -> Self;
+}
+
+impl CorsRouterExt for RluneRouter {
+    fn cors(self, metadata: CorsMetadata) -> Self {
+        self.metadata(metadata)
+    }
+}
@@ -0,0 +1,34 @@
+//! Csrf related [`RouteMetadata`]
+
+use std::borrow::Cow;
+
+use rlune_core::router::RouteMetadata;
+
+/// Csrf related [`RouteMetadata`]
+///
+/// Its presence on a route indicates that the route is protected by the csrf middleware.
+#[derive(Debug, Clone, Default)]
+pub struct CsrfMetadata {
+    /// Origins (for example `https://example.com`) which are trusted to send unsafe requests
+    ///
+    /// The origin the request was sent to is always trusted.
+    pub trusted_origins: Vec<Cow<'static, str>>,
+
+    /// Whether unsafe requests from other origins of the same site are accepted
+    pub allow_same_site: bool,
+
+    /// Whether unsafe requests have to provide a double-submit token
+    pub double_submit: bool,
+}
+
+impl RouteMetadata for CsrfMetadata {
+    fn merge(&mut self, other: &Self) {
+        for origin in &other.trusted_origins {
+            if !self.trusted_origins.contains(origin) {
+                self.trusted_origins.push(origin.clone());
+            }
+        }
+        self.allow_same_site |= other.allow_same_site;
+        self.double_submit |= other.double_submit;
+    }
+}
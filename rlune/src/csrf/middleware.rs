@@ -0,0 +1,308 @@
+//! The middleware performing the csrf checks
+
+use std::borrow::Cow;
+use std::fmt;
+
+use axum::extract::Request;
+use axum::http::header;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use rlune_core::re_exports::uuid::Uuid;
+use rlune_core::router::MatchedRoute;
+use rlune_core::session::SameSite;
+use rlune_core::session::SessionSetup;
+use rlune_core::stuff::api_error::ApiError;
+use rlune_core::stuff::schema::ApiStatusCode;
+
+use crate::csrf::metadata::CsrfMetadata;
+use crate::csrf::CSRF_COOKIE;
+use crate::csrf::CSRF_HEADER;
+
+/// Rejects unsafe requests which might have been forged by another site
+///
+/// The checks are configured by the [`CsrfMetadata`] of the matched route.
+/// Requests to routes without it pass unchecked.
+pub(crate) async fn csrf_middleware(request: Request, next: Next) -> Response {
+    let cookie = request
+        .extensions()
+        .get::<CsrfCookie>()
+        .cloned()
+        .unwrap_or_default();
+    let Some(metadata) = request
+        .extensions()
+        .get::<MatchedRoute>()
+        .and_then(|route| route.metadata().get::<CsrfMetadata>())
+        .cloned()
+    else {
+        return next.run(request).await;
+    };
+
+    if is_safe(request.method()) {
+        let issue_token = metadata.double_submit && read_token(request.headers()).is_none();
+        let mut response = next.run(request).await;
+        if issue_token {
+            if let Ok(cookie) = HeaderValue::try_from(cookie.header(Uuid::new_v4().simple())) {
+                response.headers_mut().append(header::SET_COOKIE, cookie);
+            }
+        }
+        return response;
+    }
+
+    if let Err(error) = check_request(&metadata, request.headers()) {
+        return error.into_response();
+    }
+    next.run(request).await
+}
+
+/// The attributes of the double-submit cookie
+///
+/// They are taken from the [`SessionSetup`], so the cookie is sent along the session's.
+/// Unlike the session's cookie, it is never `HttpOnly` because the frontend has to read it.
+#[derive(Debug, Clone)]
+pub(crate) struct CsrfCookie {
+    domain: Option<Cow<'static, str>>,
+    path: Cow<'static, str>,
+    secure: bool,
+    same_site: SameSite,
+}
+
+impl CsrfCookie {
+    /// Takes the cookie's attributes from the session's cookie
+    pub(crate) fn new(setup: &SessionSetup) -> Self {
+        Self {
+            domain: setup.cookie_domain.clone(),
+            path: setup.cookie_path.clone(),
+            secure: setup.cookie_secure,
+            same_site: setup.cookie_same_site,
+        }
+    }
+
+    /// Formats the `Set-Cookie` header setting the token
+    fn header(&self, token: impl fmt::Display) -> String {
+        let mut header = format!(
+            "{CSRF_COOKIE}={token}; Path={}; SameSite={}",
+            self.path, self.same_site
+        );
+        if let Some(domain) = &self.domain {
+            header.push_str("; Domain=");
+            header.push_str(domain);
+        }
+        if self.secure {
+            header.push_str("; Secure");
+        }
+        header
+    }
+}
+
+impl Default for CsrfCookie {
+    fn default() -> Self {
+        Self::new(&SessionSetup::default())
+    }
+}
+
+/// Checks whether a request's method is considered to be without side effects
+fn is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Performs the csrf checks on an unsafe request
+fn check_request(metadata: &CsrfMetadata, headers: &HeaderMap) -> Result<(), ApiError> {
+    let origin = headers
+        .get(header::ORIGIN)
+        .and_then(|value| value.to_str().ok());
+
+    let trusted = origin.is_some_and(|origin| {
+        metadata
+            .trusted_origins
+            .iter()
+            .any(|trusted| trusted == origin)
+    });
+    if !trusted {
+        match headers.get("sec-fetch-site").map(HeaderValue::as_bytes) {
+            Some(b"same-origin" | b"none") => {}
+            Some(b"same-site") if metadata.allow_same_site => {}
+            Some(_) => return Err(rejected("Cross site request")),
+            // Browsers not sending `Sec-Fetch-Site` still send `Origin`
+            None => {
+                if let Some(origin) = origin {
+                    let host = headers
+                        .get(header::HOST)
+                        .and_then(|value| value.to_str().ok());
+                    let origin_host = origin
+                        .strip_prefix("https://")
+                        .or_else(|| origin.strip_prefix("http://"));
+                    if host.is_none() || origin_host != host {
+                        return Err(rejected("Cross origin request"));
+                    }
+                }
+            }
+        }
+    }
+
+    if metadata.double_submit {
+        let cookie = read_token(headers).ok_or_else(|| rejected("Missing csrf cookie"))?;
+        let header = headers
+            .get(CSRF_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| rejected("Missing csrf header"))?;
+        if !constant_time_eq(cookie.as_bytes(), header.as_bytes()) {
+            return Err(rejected("Mismatching csrf token"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the double-submit token from the request's cookies
+fn read_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find_map(|(name, value)| (name == CSRF_COOKIE).then_some(value))
+}
+
+/// Compares two byte strings without leaking the position of the first difference
+fn constant_time_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len() && lhs.iter().zip(rhs).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
+#[track_caller]
+fn rejected(context: &'static str) -> ApiError {
+    ApiError::new(ApiStatusCode::MissingPrivileges, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn fetch_metadata() {
+        let metadata = CsrfMetadata::default();
+        assert!(check_request(&metadata, &headers(&[("sec-fetch-site", "same-origin")])).is_ok());
+        assert!(check_request(&metadata, &headers(&[("sec-fetch-site", "none")])).is_ok());
+        assert!(check_request(&metadata, &headers(&[("sec-fetch-site", "same-site")])).is_err());
+        assert!(check_request(&metadata, &headers(&[("sec-fetch-site", "cross-site")])).is_err());
+
+        let metadata = CsrfMetadata {
+            allow_same_site: true,
+            ..Default::default()
+        };
+        assert!(check_request(&metadata, &headers(&[("sec-fetch-site", "same-site")])).is_ok());
+        assert!(check_request(&metadata, &headers(&[("sec-fetch-site", "cross-site")])).is_err());
+    }
+
+    #[test]
+    fn origin_without_fetch_metadata() {
+        let metadata = CsrfMetadata::default();
+        assert!(check_request(
+            &metadata,
+            &headers(&[("origin", "https://example.com"), ("host", "example.com")])
+        )
+        .is_ok());
+        assert!(check_request(
+            &metadata,
+            &headers(&[("origin", "https://evil.org"), ("host", "example.com")])
+        )
+        .is_err());
+        assert!(check_request(&metadata, &headers(&[("origin", "https://example.com")])).is_err());
+        // Non-browser clients send neither header
+        assert!(check_request(&metadata, &HeaderMap::new()).is_ok());
+    }
+
+    #[test]
+    fn trusted_origin() {
+        let metadata = CsrfMetadata {
+            trusted_origins: vec!["https://app.example.com".into()],
+            ..Default::default()
+        };
+        assert!(check_request(
+            &metadata,
+            &headers(&[
+                ("origin", "https://app.example.com"),
+                ("sec-fetch-site", "cross-site")
+            ])
+        )
+        .is_ok());
+        assert!(check_request(
+            &metadata,
+            &headers(&[
+                ("origin", "https://evil.org"),
+                ("sec-fetch-site", "cross-site")
+            ])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn cookie_attributes() {
+        assert_eq!(
+            CsrfCookie::default().header("token"),
+            "csrf_token=token; Path=/; SameSite=Lax; Secure"
+        );
+
+        let setup = SessionSetup {
+            cookie_domain: Some("example.com".into()),
+            cookie_path: "/api".into(),
+            cookie_secure: false,
+            cookie_same_site: SameSite::Strict,
+            ..SessionSetup::disabled()
+        };
+        assert_eq!(
+            CsrfCookie::new(&setup).header("token"),
+            "csrf_token=token; Path=/api; SameSite=Strict; Domain=example.com"
+        );
+    }
+
+    #[test]
+    fn double_submit() {
+        let metadata = CsrfMetadata {
+            double_submit: true,
+            ..Default::default()
+        };
+        let same_origin = ("sec-fetch-site", "same-origin");
+        assert!(check_request(
+            &metadata,
+            &headers(&[
+                same_origin,
+                ("cookie", "session=abc; csrf_token=token"),
+                (CSRF_HEADER, "token")
+            ])
+        )
+        .is_ok());
+        assert!(check_request(
+            &metadata,
+            &headers(&[
+                same_origin,
+                ("cookie", "csrf_token=token"),
+                (CSRF_HEADER, "other")
+            ])
+        )
+        .is_err());
+        assert!(
+            check_request(&metadata, &headers(&[same_origin, (CSRF_HEADER, "token")])).is_err()
+        );
+        assert!(check_request(
+            &metadata,
+            &headers(&[same_origin, ("cookie", "csrf_token=token")])
+        )
+        .is_err());
+    }
+}
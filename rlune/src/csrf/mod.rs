@@ -0,0 +1,27 @@
+//! Protection against cross site request forgery
+//!
+//! Cookie authenticated handlers which change state can be triggered by any website
+//! the user visits. The csrf middleware rejects such requests by inspecting
+//! the `Sec-Fetch-Site` and `Origin` headers browsers attach to them.
+//!
+//! Optionally, it can also require a double-submit token:
+//! The middleware sets the [`CSRF_COOKIE`] on responses to safe requests
+//! and expects its value to be repeated in the [`CSRF_HEADER`] of unsafe ones.
+//! The cookie's `Domain`, `Path`, `Secure` and `SameSite` attributes are those of the session's cookie
+//! (see [`SessionSetup`](rlune_core::session::SessionSetup)).
+//!
+//! Protection is enabled per [`RluneRouter`](rlune_core::RluneRouter)
+//! using [`CsrfRouterExt::csrf_protection`].
+
+pub use crate::csrf::metadata::CsrfMetadata;
+pub use crate::csrf::router_ext::CsrfRouterExt;
+
+mod metadata;
+pub(crate) mod middleware;
+mod router_ext;
+
+/// Name of the cookie holding the double-submit token
+pub const CSRF_COOKIE: &str = "csrf_token";
+
+/// Name of the header which has to repeat the double-submit token
+pub const CSRF_HEADER: &str = "x-csrf-token";
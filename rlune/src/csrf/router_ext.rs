@@ -0,0 +1,33 @@
+//! [`RluneRouter`] extension trait
+
+use axum::middleware;
+use rlune_core::RluneRouter;
+
+use crate::csrf::metadata::CsrfMetadata;
+use crate::csrf::middleware::csrf_middleware;
+
+/// Extension trait for [`RluneRouter`]
+///
+/// It provides convenient methods for protecting routes against cross site request forgery.
+pub trait CsrfRouterExt {
+    /// Protects all handlers in this router against cross site request forgery
+    ///
+    /// This adds the [`CsrfMetadata`] to all handlers and applies the csrf middleware.
+    ///
+    /// Like [`RluneRouter::route_layer`], the middleware only applies to handlers
+    /// which have been added before calling this method.
+    ///
+    /// The middleware reads the [`CsrfMetadata`] of the matched route at runtime,
+    /// so metadata added later, for example by a router this one is nested in, is respected.
+    ///
+    /// # Panics
+    /// If the router doesn't contain any handlers yet.
+    fn csrf_protection(self, metadata: CsrfMetadata) -> Self;
+}
+
+impl CsrfRouterExt for RluneRouter {
+    fn csrf_protection(self, metadata: CsrfMetadata) -> Self {
+        self.metadata(metadata)
+            .route_layer(middleware::from_fn(csrf_middleware))
+    }
+}
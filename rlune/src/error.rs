@@ -11,4 +11,17 @@ pub enum RluneError {
 
     #[error("{0}")]
     Init(#[from] rlune_core::module::registry::builder::InitError),
+
+    #[error("{0}")]
+    Shutdown(#[from] rlune_core::module::registry::shutdown::ShutdownError),
+
+    #[error("{0}")]
+    Module(#[from] rlune_core::TryGlobalError),
+
+    #[error("No address to listen on has been added")]
+    NoListener,
+
+    #[cfg(feature = "tls")]
+    #[error("{0}")]
+    Tls(#[from] crate::tls::TlsError),
 }
@@ -8,19 +8,38 @@ use signal_hook_tokio::Signals;
 use tracing::debug;
 use tracing::warn;
 
-/// Constructs a future with resolves after receiving a [termination signal](signal_hook::consts::TERM_SIGNALS)
+use crate::shutdown::ShutdownHandle;
+
+/// Constructs a future which forwards [termination signals](signal_hook::consts::TERM_SIGNALS) to a [`ShutdownHandle`]
+///
+/// The first signal requests a graceful shutdown, the second one forces it.
 ///
 /// # Errors
 /// if the signal handler can't be registered
-pub fn wait_for_signal() -> io::Result<impl Future<Output = ()>> {
+pub fn forward_signals(handle: ShutdownHandle) -> io::Result<impl Future<Output = ()>> {
     let mut signals = Signals::new(signal_hook::consts::TERM_SIGNALS)?;
     Ok(async move {
-        let handle = signals.handle();
-        let signal = poll_fn(|ctx| Pin::new(&mut signals).poll_next(ctx)).await;
-        match signal {
-            Some(sig_num) => debug!(signal.number = sig_num, "Shutting down"),
-            None => warn!("Signal stream terminated, this is unexpected!"),
+        let signals_handle = signals.handle();
+        loop {
+            match poll_fn(|ctx| Pin::new(&mut signals).poll_next(ctx)).await {
+                Some(sig_num) if handle.is_requested() => {
+                    warn!(
+                        signal.number = sig_num,
+                        "Received second signal, forcing shutdown"
+                    );
+                    handle.force();
+                    break;
+                }
+                Some(sig_num) => {
+                    debug!(signal.number = sig_num, "Shutting down");
+                    handle.shutdown();
+                }
+                None => {
+                    warn!("Signal stream terminated, this is unexpected!");
+                    break;
+                }
+            }
         }
-        handle.close();
+        signals_handle.close();
     })
 }
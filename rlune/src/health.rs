@@ -0,0 +1,105 @@
+//! Handlers reporting the application's health for load balancers and orchestrators
+//!
+//! They are not mounted automatically.
+//! Add [`router`] to your routes and point your liveness and readiness probes at them:
+//!
+//! ```no_run
+//! # use rlune::core::RluneRouter;
+//! RluneRouter::new().merge(rlune::health::router());
+//! ```
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::Json;
+use rlune_core::handler::response_body::ResponseBody;
+use rlune_core::handler::response_body::ShouldBeResponseBody;
+use rlune_core::re_exports::mime;
+use rlune_core::re_exports::mime::Mime;
+use rlune_core::re_exports::schemars::schema::Schema;
+use rlune_core::registry::HealthReport;
+use rlune_core::registry::HealthStatus;
+use rlune_core::registry::Registry;
+use rlune_core::schema_generator::SchemaGenerator;
+use rlune_core::RluneRouter;
+use rlune_macros::get;
+
+/// The time each module's health check is granted
+///
+/// It is shorter than the default timeout of Kubernetes' probes (1 second)
+/// to still respond with a meaningful report if a check hangs.
+pub const CHECK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Constructs a router containing the health handlers
+pub fn router() -> RluneRouter {
+    RluneRouter::new().handler(get_health).handler(get_ready)
+}
+
+/// Reports whether the application is alive
+///
+/// Responds with `503 Service Unavailable` if any module is unhealthy.
+#[get("/health", core_crate = "crate::core")]
+pub async fn get_health() -> HealthResponse {
+    let report = Registry::global().health_report(CHECK_TIMEOUT).await;
+    HealthResponse {
+        ok: report.status != HealthStatus::Unhealthy,
+        report,
+    }
+}
+
+/// Reports whether the application is ready to serve requests
+///
+/// Responds with `503 Service Unavailable` if any module is unhealthy
+/// or the application is shutting down.
+#[get("/ready", core_crate = "crate::core")]
+pub async fn get_ready() -> HealthResponse {
+    let registry = Registry::global();
+    let report = registry.health_report(CHECK_TIMEOUT).await;
+    HealthResponse {
+        ok: report.status != HealthStatus::Unhealthy && !registry.is_shutting_down(),
+        report,
+    }
+}
+
+/// Response of the health handlers
+///
+/// The [`HealthReport`] is sent as json
+/// with either `200 Ok` or `503 Service Unavailable`.
+#[derive(Debug)]
+pub struct HealthResponse {
+    /// Whether the check succeeded
+    pub ok: bool,
+
+    /// The modules' health
+    pub report: HealthReport,
+}
+
+impl IntoResponse for HealthResponse {
+    fn into_response(self) -> Response {
+        let status = if self.ok {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        (status, Json(self.report)).into_response()
+    }
+}
+
+impl ShouldBeResponseBody for HealthResponse {}
+impl ResponseBody for HealthResponse {
+    fn body(generator: &mut SchemaGenerator) -> Vec<(StatusCode, Option<(Mime, Option<Schema>)>)> {
+        let schema = generator.generate::<HealthReport>();
+        vec![
+            (
+                StatusCode::OK,
+                Some((mime::APPLICATION_JSON, Some(schema.clone()))),
+            ),
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Some((mime::APPLICATION_JSON, Some(schema))),
+            ),
+        ]
+    }
+}
@@ -0,0 +1,90 @@
+//! Handlers exposing the registered modules for debugging and administration
+//!
+//! They are not mounted automatically.
+//! Add [`router`] to your routes, preferably behind some authentication:
+//!
+//! ```no_run
+//! # use rlune::core::RluneRouter;
+//! RluneRouter::new().nest("/admin", rlune::introspection::router());
+//! ```
+
+use axum::Json;
+use rlune_core::registry::tasks::TaskInfo;
+use rlune_core::registry::ModuleInfo;
+use rlune_core::registry::Registry;
+use rlune_core::stuff::api_error::ApiError;
+use rlune_core::stuff::api_error::ApiResult;
+use rlune_core::RluneRouter;
+use rlune_macros::get;
+use rlune_macros::post;
+use rlune_macros::put;
+
+use crate::logging;
+use crate::logging::LoggingError;
+
+/// Constructs a router containing the introspection handlers
+pub fn router() -> RluneRouter {
+    RluneRouter::new()
+        .handler(get_modules)
+        .handler(get_module_graph)
+        .handler(get_tasks)
+        .handler(reload_modules)
+        .handler(get_log_filter)
+        .handler(set_log_filter)
+}
+
+/// Lists all modules in the order they have been initialized
+///
+/// Includes their dependencies, setup and how long their initialization took.
+#[get("/modules", core_crate = "crate::core")]
+pub async fn get_modules() -> Json<Vec<ModuleInfo>> {
+    Json(Registry::global().module_infos())
+}
+
+/// Renders the module dependency graph in Graphviz' dot language
+#[get("/modules/graph", core_crate = "crate::core")]
+pub async fn get_module_graph() -> String {
+    Registry::global().module_graph_dot()
+}
+
+/// Lists all background tasks spawned by modules
+///
+/// Includes their status, restarts and last error.
+#[get("/tasks", core_crate = "crate::core")]
+pub async fn get_tasks() -> Json<Vec<TaskInfo>> {
+    Json(Registry::global().task_infos())
+}
+
+/// Reloads every module's configuration
+///
+/// (see [`Registry::reload`])
+#[post("/modules/reload", core_crate = "crate::core")]
+pub async fn reload_modules() -> ApiResult<()> {
+    Registry::global()
+        .reload()
+        .await
+        .map_err(|error| ApiError::server_error("Failed to reload modules").with_source(error))
+}
+
+/// Gets the filter of rlune's tracing subscriber
+///
+/// (see [`logging::filter`])
+#[get("/logging/filter", core_crate = "crate::core")]
+pub async fn get_log_filter() -> ApiResult<String> {
+    logging::filter().ok_or_else(|| ApiError::server_error("Rlune's subscriber is not installed"))
+}
+
+/// Replaces the filter of rlune's tracing subscriber
+///
+/// The body uses the syntax of `RUST_LOG` (for example `info,rorm=debug`).
+///
+/// (see [`logging::set_filter`])
+#[put("/logging/filter", core_crate = "crate::core")]
+pub async fn set_log_filter(directives: String) -> ApiResult<()> {
+    logging::set_filter(directives.trim()).map_err(|error| match error {
+        LoggingError::InvalidFilter(_) => {
+            ApiError::bad_request("Invalid log filter").with_source(error)
+        }
+        _ => ApiError::server_error("Failed to set log filter").with_source(error),
+    })
+}
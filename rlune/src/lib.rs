@@ -1,6 +1,7 @@
 #[cfg(feature = "contrib")]
 pub mod contrib {
     pub use rlune_contrib_auth as auth;
+    pub use rlune_contrib_jobs as jobs;
     // pub use rlune_contrib_tracing as tracing;
 }
 
@@ -21,14 +22,31 @@ pub mod core {
 
 pub use crate::rlune::*;
 
+#[cfg(feature = "cors")]
+pub mod cors;
+#[cfg(feature = "csrf")]
+pub mod csrf;
 pub mod error;
 #[cfg(feature = "graceful-shutdown")]
 mod graceful_shutdown;
+pub mod health;
+pub mod introspection;
+pub mod listener;
+pub mod logging;
 mod macro_docs;
+pub mod metrics;
 #[cfg(feature = "openapi")]
 pub mod openapi;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
 #[cfg(feature = "panic-hook")]
 pub mod panic_hook;
+#[cfg(feature = "reload-signal")]
+mod reload_signal;
+mod request_tracing;
 mod rlune;
+pub mod shutdown;
+#[cfg(feature = "tls")]
+pub mod tls;
 
 pub use macro_docs::*;
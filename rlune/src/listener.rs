@@ -0,0 +1,184 @@
+//! Addresses the webserver can listen on
+//!
+//! The same routes can be served on several addresses
+//! and additional routers can be served on their own addresses:
+//!
+//! ```no_run
+//! # use std::net::SocketAddr;
+//! # use rlune::core::RluneRouter;
+//! # use rlune::listener::ListenAddr;
+//! # async fn start(mut builder: rlune::RouterBuilder, admin: RluneRouter) -> Result<(), rlune::error::RluneError> {
+//! builder
+//!     .listen("0.0.0.0:8080".parse::<SocketAddr>().unwrap())
+//!     .listen("[::]:8080".parse::<SocketAddr>().unwrap())
+//!     .listen_router(ListenAddr::Unix("/run/app/admin.sock".into()), admin)
+//!     .serve()
+//!     .await
+//! # }
+//! ```
+
+#[cfg(unix)]
+use std::env;
+use std::fmt;
+#[cfg(unix)]
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::io::FromRawFd;
+#[cfg(unix)]
+use std::os::unix::io::OwnedFd;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::process;
+#[cfg(unix)]
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::sync::PoisonError;
+
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+#[cfg(unix)]
+use tracing::debug;
+
+/// The first file descriptor passed by systemd's socket activation
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// The file descriptors passed by systemd which haven't been used yet
+///
+/// `None` until the environment has been read.
+#[cfg(unix)]
+static SYSTEMD_FDS: Mutex<Option<Vec<Option<RawFd>>>> = Mutex::new(None);
+
+/// An address the webserver can listen on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    /// A tcp socket
+    Tcp(SocketAddr),
+
+    /// A unix domain socket at the given path
+    ///
+    /// A stale socket left over by a previous run is removed before binding
+    /// and the socket is removed again once the server stopped.
+    #[cfg(unix)]
+    Unix(PathBuf),
+
+    /// A socket passed by systemd's socket activation
+    ///
+    /// The index refers to the order of the sockets in the `.socket` unit
+    /// i.e. `0` is the first socket described by `$LISTEN_FDS`.
+    /// Both tcp and unix domain sockets are supported.
+    #[cfg(unix)]
+    Systemd(usize),
+}
+
+impl From<SocketAddr> for ListenAddr {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Tcp(addr)
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            #[cfg(unix)]
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+            #[cfg(unix)]
+            Self::Systemd(index) => write!(f, "systemd:{index}"),
+        }
+    }
+}
+
+/// A bound listener ready to be served
+pub(crate) enum BoundListener {
+    Tcp(TcpListener),
+
+    /// A unix domain socket and the path to remove after serving
+    #[cfg(unix)]
+    Unix(UnixListener, Option<PathBuf>),
+}
+
+/// Binds a listener to `addr`
+pub(crate) async fn bind(addr: &ListenAddr) -> io::Result<BoundListener> {
+    match addr {
+        ListenAddr::Tcp(addr) => Ok(BoundListener::Tcp(TcpListener::bind(addr).await?)),
+        #[cfg(unix)]
+        ListenAddr::Unix(path) => {
+            match fs::symlink_metadata(path) {
+                Ok(metadata) if metadata.file_type().is_socket() => {
+                    debug!(path = %path.display(), "Removing stale unix socket");
+                    fs::remove_file(path)?;
+                }
+                _ => {}
+            }
+            Ok(BoundListener::Unix(
+                UnixListener::bind(path)?,
+                Some(path.clone()),
+            ))
+        }
+        #[cfg(unix)]
+        ListenAddr::Systemd(index) => from_systemd(*index),
+    }
+}
+
+/// Takes a socket passed by systemd
+#[cfg(unix)]
+fn from_systemd(index: usize) -> io::Result<BoundListener> {
+    let fd = SYSTEMD_FDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(systemd_fds)
+        .get_mut(index)
+        .and_then(Option::take)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("systemd didn't pass a socket with index {index} or it is already in use"),
+            )
+        })?;
+
+    // SAFETY: systemd passed this file descriptor to our process
+    // and it has just been removed from `SYSTEMD_FDS`, so nothing else owns it.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let listener = std::net::TcpListener::from(fd);
+    // Retrieving a tcp address fails for unix domain sockets
+    if listener.local_addr().is_ok() {
+        listener.set_nonblocking(true)?;
+        return Ok(BoundListener::Tcp(TcpListener::from_std(listener)?));
+    }
+    let listener = std::os::unix::net::UnixListener::from(OwnedFd::from(listener));
+    listener.set_nonblocking(true)?;
+    Ok(BoundListener::Unix(UnixListener::from_std(listener)?, None))
+}
+
+/// Reads the file descriptors passed by systemd from the environment
+///
+/// See `sd_listen_fds(3)`.
+#[cfg(unix)]
+fn systemd_fds() -> Vec<Option<RawFd>> {
+    let for_us = env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        == Some(process::id());
+    if !for_us {
+        return Vec::new();
+    }
+
+    let count = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<RawFd>().ok())
+        .unwrap_or(0);
+    debug!(count, "Received sockets from systemd");
+    (0..count)
+        .map(|offset| Some(SD_LISTEN_FDS_START + offset))
+        .collect()
+}
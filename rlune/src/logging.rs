@@ -0,0 +1,262 @@
+//! Configuration of rlune's tracing subscriber
+//!
+//! [`Rlune::new`](crate::Rlune::new) installs a subscriber writing plain text to stdout.
+//! Use [`Rlune::with_logging`](crate::Rlune::with_logging) to configure it:
+//!
+//! ```no_run
+//! # use rlune::logging::{LogFileSetup, LogFormat, LoggingSetup};
+//! # use rlune::Rlune;
+//! # use tracing::Level;
+//! let mut logging = LoggingSetup::default();
+//! logging.format = LogFormat::Json;
+//! logging.file = Some(LogFileSetup::new("/var/log/app", "app.log"));
+//! logging.module_level("rorm", Level::WARN);
+//! Rlune::with_logging(logging);
+//! ```
+//!
+//! If the `RUST_LOG` environment variable is set, it replaces the configured filter.
+//!
+//! The filter can be changed at runtime using [`set_filter`]
+//! or the handlers in [`introspection`](crate::introspection).
+
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use thiserror::Error;
+use tracing::warn;
+use tracing::Level;
+use tracing::Subscriber;
+use tracing_appender::rolling::RollingFileAppender;
+pub use tracing_appender::rolling::Rotation;
+use tracing_subscriber::filter::ParseError;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::Layer;
+use tracing_subscriber::Registry;
+
+/// Handle to swap the installed subscriber's filter
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Declares how rlune's tracing subscriber is set up
+#[derive(Debug, Clone)]
+pub struct LoggingSetup {
+    /// The format of the output written to stdout
+    pub format: LogFormat,
+
+    /// Whether the stdout output is colored
+    pub ansi: bool,
+
+    /// The level of events which are not matched by a more specific directive
+    pub level: Level,
+
+    /// Additional filter directives like `rorm=warn` or `my_app::auth=debug`
+    ///
+    /// See [`EnvFilter`] for their syntax.
+    pub directives: Vec<String>,
+
+    /// Writes the output to rotated files in addition to stdout
+    pub file: Option<LogFileSetup>,
+
+    /// Exports spans to an OpenTelemetry collector
+    ///
+    /// See [`otel`](crate::otel) for details.
+    /// [`Rlune::with_logging`](crate::Rlune::with_logging) has to be called within a tokio runtime.
+    #[cfg(feature = "opentelemetry")]
+    pub opentelemetry: Option<crate::otel::OtelSetup>,
+}
+
+impl Default for LoggingSetup {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Plain,
+            ansi: true,
+            level: Level::INFO,
+            directives: Vec::new(),
+            file: None,
+            #[cfg(feature = "opentelemetry")]
+            opentelemetry: None,
+        }
+    }
+}
+
+impl LoggingSetup {
+    /// Sets the level of a module's events
+    pub fn module_level(&mut self, module: impl AsRef<str>, level: Level) -> &mut Self {
+        self.directives.push(format!("{}={level}", module.as_ref()));
+        self
+    }
+
+    /// Constructs the filter described by `self` unless `RUST_LOG` is set
+    fn filter(&self) -> Result<EnvFilter, ParseError> {
+        if let Ok(filter) = EnvFilter::try_from_default_env() {
+            return Ok(filter);
+        }
+        let mut directives = self.level.to_string();
+        for directive in &self.directives {
+            let _ = write!(directives, ",{directive}");
+        }
+        EnvFilter::try_new(directives)
+    }
+}
+
+/// The format of log output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// A single line per event
+    #[default]
+    Plain,
+
+    /// Multiple lines per event which is easier to read for humans
+    Pretty,
+
+    /// A json object per line
+    Json,
+}
+
+/// Declares how log files are written
+#[derive(Debug, Clone)]
+pub struct LogFileSetup {
+    /// The directory to write the files into
+    pub directory: PathBuf,
+
+    /// The files' name
+    ///
+    /// Rotated files are suffixed with their date.
+    pub file_name: String,
+
+    /// How often a new file is started
+    pub rotation: Rotation,
+
+    /// The format of the files' content
+    pub format: LogFormat,
+}
+
+impl LogFileSetup {
+    /// Constructs a setup writing json to a daily rotated file
+    pub fn new(directory: impl Into<PathBuf>, file_name: impl Into<String>) -> Self {
+        Self {
+            directory: directory.into(),
+            file_name: file_name.into(),
+            rotation: Rotation::DAILY,
+            format: LogFormat::Json,
+        }
+    }
+}
+
+/// Error returned when changing the log filter
+#[derive(Debug, Error)]
+pub enum LoggingError {
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(#[from] ParseError),
+
+    #[error("Rlune's subscriber is not installed")]
+    NotInstalled,
+
+    #[error("Failed to swap the filter: {0}")]
+    Reload(#[from] reload::Error),
+}
+
+/// Installs rlune's subscriber
+///
+/// Returns `false` if another subscriber has already been installed.
+pub(crate) fn init(setup: &LoggingSetup) -> bool {
+    let (filter, filter_error) = match setup.filter() {
+        Ok(filter) => (filter, None),
+        Err(error) => (EnvFilter::new(Level::INFO.as_str()), Some(error)),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let (file, file_error) = match &setup.file {
+        Some(file) => {
+            match RollingFileAppender::builder()
+                .rotation(file.rotation.clone())
+                .filename_prefix(&file.file_name)
+                .build(&file.directory)
+            {
+                Ok(appender) => (Some(format_layer(file.format, appender, false)), None),
+                Err(error) => (None, Some(error)),
+            }
+        }
+        None => (None, None),
+    };
+
+    // The filter only applies to the log output, the exported spans have their own one
+    let output = format_layer(setup.format, std::io::stdout, setup.ansi)
+        .and_then(file)
+        .with_filter(filter);
+
+    #[cfg(feature = "opentelemetry")]
+    let (otel, otel_error) = match setup.opentelemetry.as_ref().map(crate::otel::layer) {
+        Some(Ok(layer)) => (Some(layer), None),
+        Some(Err(error)) => (None, Some(error)),
+        None => (None, None),
+    };
+    #[cfg(not(feature = "opentelemetry"))]
+    let otel = tracing_subscriber::layer::Identity::new();
+
+    let installed = tracing_subscriber::registry()
+        .with(output)
+        .with(otel)
+        .try_init()
+        .is_ok();
+    if !installed {
+        return false;
+    }
+
+    let _ = FILTER.set(handle);
+    if let Some(error) = filter_error {
+        warn!(error.display = %error, "Invalid log filter, falling back to 'info'");
+    }
+    if let Some(error) = file_error {
+        warn!(error.display = %error, "Failed to open the log file, only logging to stdout");
+    }
+    #[cfg(feature = "opentelemetry")]
+    if let Some(error) = otel_error {
+        warn!(error.display = %error, "Failed to set up the export of traces");
+    }
+    true
+}
+
+/// Constructs a layer writing events in `format` to `writer`
+fn format_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Plain => layer.boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Replaces the filter of rlune's subscriber
+///
+/// The `directives` use the syntax of [`EnvFilter`] (for example `info,rorm=debug`).
+///
+/// # Errors
+/// - if the directives are invalid
+/// - if rlune's subscriber has not been installed
+pub fn set_filter(directives: &str) -> Result<(), LoggingError> {
+    let filter = EnvFilter::try_new(directives)?;
+    FILTER
+        .get()
+        .ok_or(LoggingError::NotInstalled)?
+        .reload(filter)?;
+    Ok(())
+}
+
+/// Gets the current filter of rlune's subscriber
+///
+/// Returns `None` if rlune's subscriber has not been installed.
+pub fn filter() -> Option<String> {
+    FILTER.get()?.with_current(ToString::to_string).ok()
+}
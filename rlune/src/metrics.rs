@@ -0,0 +1,122 @@
+//! Metrics about the handled requests in Prometheus' text format
+//!
+//! Every request is recorded unless disabled using
+//! [`RouterBuilder::metrics`](crate::RouterBuilder::metrics):
+//! - `rlune_http_requests_total` counts the requests by method, route, handler and status
+//! - `rlune_http_request_duration_seconds` is a histogram of their latency by method, route and handler
+//! - `rlune_http_requests_in_flight` is the number of requests currently being handled
+//!
+//! The route is the path template the request matched (for example `/users/{uuid}`)
+//! and the handler is the handler function's identifier.
+//! Both are empty for requests which didn't match any route.
+//!
+//! Additionally, the core reports the error responses by their [`ApiStatusCode`](crate::core::stuff::schema::ApiStatusCode)
+//! and modules may register their own metrics using [`Metrics`].
+//!
+//! The metrics are not exposed automatically.
+//! Add [`router`] to your routes, preferably on a separate listener or behind some authentication:
+//!
+//! ```no_run
+//! # use rlune::core::RluneRouter;
+//! RluneRouter::new().merge(rlune::metrics::router());
+//! ```
+
+use std::time::Instant;
+
+use axum::extract::Request;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+pub use rlune_core::metrics::*;
+use rlune_core::router::MatchedRoute;
+use rlune_core::RluneRouter;
+use rlune_macros::get;
+
+/// Constructs a router containing the [`get_metrics`] handler
+pub fn router() -> RluneRouter {
+    RluneRouter::new().handler(get_metrics)
+}
+
+/// Renders all metrics in Prometheus' text format
+#[get("/metrics", core_crate = "crate::core")]
+pub async fn get_metrics() -> String {
+    Metrics::global().render()
+}
+
+/// Decrements the in-flight gauge when the request is done or its future is dropped
+struct InFlight(Gauge);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Gets the label for a request's method
+///
+/// Extension methods are reported as `OTHER`, so clients can't create arbitrary many label values.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::PATCH => "PATCH",
+        Method::TRACE => "TRACE",
+        _ => "OTHER",
+    }
+}
+
+/// Records a single request
+pub(crate) async fn record_request(request: Request, next: Next) -> Response {
+    let metrics = Metrics::global();
+    let method = method_label(request.method());
+    let (route, handler) = request
+        .extensions()
+        .get::<MatchedRoute>()
+        .map(|route| (route.path().to_string(), route.handler().ident))
+        .unwrap_or_default();
+
+    let in_flight = metrics.gauge(
+        "rlune_http_requests_in_flight",
+        "Number of requests currently being handled",
+        &[],
+    );
+    in_flight.inc();
+    let in_flight = InFlight(in_flight);
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = start.elapsed();
+    drop(in_flight);
+
+    metrics
+        .histogram(
+            "rlune_http_request_duration_seconds",
+            "Time it took to handle a request",
+            &[
+                ("method", method),
+                ("route", route.as_str()),
+                ("handler", handler),
+            ],
+            DEFAULT_BUCKETS,
+        )
+        .observe(elapsed.as_secs_f64());
+    metrics
+        .counter(
+            "rlune_http_requests_total",
+            "Number of handled requests",
+            &[
+                ("method", method),
+                ("route", route.as_str()),
+                ("handler", handler),
+                ("status", response.status().as_str()),
+            ],
+        )
+        .inc();
+
+    response
+}
@@ -0,0 +1,251 @@
+//! Export of traces to an OpenTelemetry collector
+//!
+//! Set [`LoggingSetup::opentelemetry`](crate::logging::LoggingSetup::opentelemetry)
+//! to export spans using OTLP over http:
+//!
+//! ```no_run
+//! # use rlune::logging::LoggingSetup;
+//! # use rlune::otel::OtelSetup;
+//! # use rlune::Rlune;
+//! # fn main() {
+//! let mut logging = LoggingSetup::default();
+//! logging.opentelemetry = Some(OtelSetup::new("my-service"));
+//! Rlune::with_logging(logging);
+//! # }
+//! ```
+//!
+//! The exported spans include
+//! - a span per request which continues the trace of the request's `traceparent` header
+//! - the spans of the modules' initialization
+//! - rorm's statements which are attached as events to the span executing them
+//!
+//! Outgoing requests should propagate the trace using
+//! [`trace_context::inject`](rlune_core::stuff::trace_context::inject).
+
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use opentelemetry::global;
+use opentelemetry::trace::TraceError;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::runtime;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use thiserror::Error;
+use tracing::warn;
+use tracing::Subscriber;
+use tracing_subscriber::filter::ParseError;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::Layer;
+
+/// The provider exporting the spans
+///
+/// It is taken to flush the remaining spans on shutdown.
+static PROVIDER: Mutex<Option<TracerProvider>> = Mutex::new(None);
+
+/// Declares how traces are exported
+#[derive(Debug, Clone)]
+pub struct OtelSetup {
+    /// The name identifying this service in the traces
+    pub service_name: String,
+
+    /// The url of the collector's OTLP/http traces endpoint
+    ///
+    /// Defaults to the `OTEL_EXPORTER_OTLP_ENDPOINT` environment variable
+    /// or `http://localhost:4318/v1/traces`.
+    pub endpoint: Option<String>,
+
+    /// The filter selecting the exported spans
+    ///
+    /// It is independent of the log's filter and uses the syntax of [`EnvFilter`].
+    /// The default includes the modules' initialization and rorm's statements.
+    pub filter: String,
+}
+
+impl OtelSetup {
+    /// Constructs a setup exporting to the default endpoint
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self {
+            service_name: service_name.into(),
+            endpoint: None,
+            filter: "info,rlune_core::module=trace,rorm_db::executor=debug".to_string(),
+        }
+    }
+}
+
+/// Error returned when setting up the export
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum OtelError {
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(#[from] ParseError),
+
+    #[error("Failed to construct the exporter: {0}")]
+    Exporter(#[from] TraceError),
+}
+
+/// Constructs the layer exporting spans
+///
+/// This also installs the W3C trace context propagator.
+/// It has to be called from within a tokio runtime.
+pub(crate) fn layer<S>(setup: &OtelSetup) -> Result<Box<dyn Layer<S> + Send + Sync>, OtelError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+{
+    let filter = EnvFilter::try_new(&setup.filter)?;
+
+    let mut exporter = SpanExporter::builder().with_http();
+    if let Some(endpoint) = &setup.endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+    }
+    let exporter = exporter.build()?;
+
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            setup.service_name.clone(),
+        )]))
+        .build();
+    let tracer = provider.tracer("rlune");
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_tracer_provider(provider.clone());
+    *PROVIDER.lock().unwrap_or_else(PoisonError::into_inner) = Some(provider);
+
+    Ok(tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .with_filter(filter)
+        .boxed())
+}
+
+/// Flushes the spans which haven't been exported yet and stops the export
+pub(crate) async fn shutdown() {
+    let provider = PROVIDER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    if let Some(provider) = provider {
+        // Shutting down blocks until the batch exporter, which runs on the runtime, has finished
+        let result = tokio::task::spawn_blocking(move || provider.shutdown())
+            .await
+            .unwrap_or_else(|error| Err(TraceError::Other(Box::new(error))));
+        if let Err(error) = result {
+            warn!(
+                error.display = %error,
+                error.debug = ?error,
+                "Failed to flush the remaining spans"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::body::Bytes;
+    use axum::extract::Request;
+    use axum::middleware;
+    use axum::routing::get;
+    use axum::Router;
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use prost::Message;
+    use rlune_core::stuff::trace_context;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::request_tracing::trace_request;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_ID: &str = "00f067aa0ba902b7";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// Starts an OTLP/http receiver returning its endpoint and the received exports
+    async fn start_receiver() -> (String, mpsc::UnboundedReceiver<ExportTraceServiceRequest>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let router = Router::new().fallback(move |body: Bytes| {
+            let sender = sender.clone();
+            async move {
+                let export = ExportTraceServiceRequest::decode(body).unwrap();
+                let _ = sender.send(export);
+            }
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        (endpoint, receiver)
+    }
+
+    /// Returns the `traceparent` [`trace_context::inject`] produces within the request's span
+    async fn propagated_traceparent() -> String {
+        let mut traceparent = String::new();
+        trace_context::inject(|name, value| {
+            if name == "traceparent" {
+                traceparent = value;
+            }
+        });
+        traceparent
+    }
+
+    #[tokio::test]
+    async fn exports_request_spans() {
+        let (endpoint, mut receiver) = start_receiver().await;
+        let mut setup = OtelSetup::new("rlune-test");
+        setup.endpoint = Some(endpoint);
+        let subscriber = tracing_subscriber::registry().with(layer(&setup).unwrap());
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        let response = Router::new()
+            .route("/", get(propagated_traceparent))
+            .layer(middleware::from_fn(trace_request))
+            .oneshot(
+                Request::get("/")
+                    .header("traceparent", format!("00-{TRACE_ID}-{PARENT_ID}-01"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let propagated = String::from_utf8(body.to_vec()).unwrap();
+
+        drop(guard);
+        shutdown().await;
+
+        let mut spans = Vec::new();
+        while let Ok(export) = receiver.try_recv() {
+            spans.extend(
+                export
+                    .resource_spans
+                    .into_iter()
+                    .flat_map(|resource| resource.scope_spans)
+                    .flat_map(|scope| scope.spans),
+            );
+        }
+        let span = spans
+            .iter()
+            .find(|span| span.name == "request")
+            .expect("The request span should have been exported");
+
+        // The span continues the incoming trace
+        assert_eq!(hex(&span.trace_id), TRACE_ID);
+        assert_eq!(hex(&span.parent_span_id), PARENT_ID);
+
+        // Outgoing requests continue the trace with the request span as parent
+        let span_id = hex(&span.span_id);
+        assert_eq!(propagated, format!("00-{TRACE_ID}-{span_id}-01"));
+    }
+}
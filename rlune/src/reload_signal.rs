@@ -0,0 +1,29 @@
+use std::future::poll_fn;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+
+use futures_lite::Stream;
+use rlune_core::registry::Registry;
+use signal_hook::consts::SIGHUP;
+use signal_hook_tokio::Signals;
+use tracing::debug;
+use tracing::warn;
+
+/// Constructs a future which [reloads every module](Registry::reload) upon receiving `SIGHUP`
+///
+/// The future only resolves if the signal stream terminates.
+///
+/// # Errors
+/// if the signal handler can't be registered
+pub fn reload_on_signal() -> io::Result<impl Future<Output = ()>> {
+    let mut signals = Signals::new([SIGHUP])?;
+    Ok(async move {
+        while let Some(sig_num) = poll_fn(|ctx| Pin::new(&mut signals).poll_next(ctx)).await {
+            debug!(signal.number = sig_num, "Reloading modules");
+            // Errors are already logged by the registry
+            let _ = Registry::global().reload().await;
+        }
+        warn!("Signal stream terminated, this is unexpected!");
+    })
+}
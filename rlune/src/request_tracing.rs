@@ -0,0 +1,64 @@
+//! Middleware assigning request ids, opening a span per request and writing the access log
+//!
+//! The span continues the trace of the request's `traceparent` header
+//! if the `opentelemetry` feature is enabled.
+
+use std::time::Instant;
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use rlune_core::router::MatchedRoute;
+use rlune_core::stuff::request_id::RequestId;
+use rlune_core::stuff::request_id::REQUEST_ID_HEADER;
+use rlune_core::stuff::trace_context;
+use tracing::info;
+use tracing::info_span;
+use tracing::Instrument;
+
+/// Traces a single request
+///
+/// The request id is taken from the request's [`REQUEST_ID_HEADER`] if it is valid
+/// and generated otherwise. It is added to the request's extensions and the response's headers.
+pub(crate) async fn trace_request(mut request: Request, next: Next) -> Response {
+    let start = Instant::now();
+
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(RequestId::from_header)
+        .unwrap_or_else(RequestId::generate);
+    request.extensions_mut().insert(request_id.clone());
+
+    let route = request.extensions().get::<MatchedRoute>();
+    let span = info_span!(
+        "request",
+        http.method = %request.method(),
+        http.route = route.map(MatchedRoute::path),
+        handler = route.map(|route| route.handler().ident),
+        request.id = %request_id,
+    );
+    trace_context::extract(&span, request.headers());
+    let path = request.uri().path().to_string();
+
+    let mut response = request_id
+        .clone()
+        .scope(next.run(request))
+        .instrument(span.clone())
+        .await;
+
+    span.in_scope(|| {
+        info!(
+            http.path = path.as_str(),
+            http.status = response.status().as_u16(),
+            latency_ms = start.elapsed().as_secs_f64() * 1000.0,
+            "Request handled"
+        )
+    });
+
+    if let Ok(value) = HeaderValue::from_str(request_id.as_str()) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
@@ -1,22 +1,64 @@
+use std::convert::Infallible;
+use std::fmt::Debug;
+#[cfg(unix)]
+use std::fs;
 use std::io;
 use std::mem;
 use std::net::SocketAddr;
+use std::pin::pin;
+#[cfg(feature = "cors")]
+use std::sync::Arc;
 use std::sync::OnceLock;
+use std::time::Duration;
 
+use axum::body::Body;
+use axum::extract::Request;
+use axum::middleware;
+use axum::response::IntoResponse;
+use axum::routing::Route;
+use axum::serve::Listener;
+#[cfg(feature = "csrf")]
+use axum::Extension;
+use axum::Router;
+use futures_lite::future;
+use hyper::body::Incoming;
+use hyper_util::rt::TokioExecutor;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
 use rlune_core::registry::builder::RegistryBuilder;
+use rlune_core::registry::ProvidesInterface;
+use rlune_core::registry::Registry;
 use rlune_core::router::RluneRoute;
 use rlune_core::session;
+use rlune_core::session::SessionSetup;
 use rlune_core::RluneRouter;
-use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tower::Layer;
+use tower::Service;
+use tower::ServiceExt;
 use tracing::debug;
 use tracing::info;
-use tracing::Level;
-use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::EnvFilter;
+use tracing::trace;
 
 use crate::core::Module;
+#[cfg(feature = "cors")]
+use crate::cors::CorsMetadata;
 use crate::error::RluneError;
+use crate::listener;
+use crate::listener::BoundListener;
+use crate::listener::ListenAddr;
+use crate::logging;
+use crate::logging::LoggingSetup;
+use crate::metrics;
+use crate::request_tracing;
+use crate::shutdown::ShutdownHandle;
+use crate::shutdown::ShutdownPhase;
+use crate::shutdown::ShutdownSetup;
+#[cfg(feature = "tls")]
+use crate::tls::TlsListener;
+#[cfg(feature = "tls")]
+use crate::tls::TlsSetup;
 
 /// Global handle to the running rlune server
 ///
@@ -24,12 +66,22 @@ use crate::error::RluneError;
 #[non_exhaustive]
 pub struct Rlune {
     routes: Vec<RluneRoute>,
+    shutdown_handle: ShutdownHandle,
 }
 
 impl Rlune {
     /// Constructs the builder to initialize and start `Rlune`
+    ///
+    /// This installs rlune's tracing subscriber with its default [`LoggingSetup`].
     pub fn new() -> ModuleBuilder {
-        ModuleBuilder::new()
+        ModuleBuilder::new(&LoggingSetup::default())
+    }
+
+    /// Constructs the builder to initialize and start `Rlune` with a custom [`LoggingSetup`]
+    ///
+    /// See [`logging`](crate::logging) for details.
+    pub fn with_logging(logging: LoggingSetup) -> ModuleBuilder {
+        ModuleBuilder::new(&logging)
     }
 
     /// Gets the global `Rlune` instance
@@ -51,6 +103,13 @@ impl Rlune {
         INSTANCE.get()
     }
 
+    /// Gets a handle to stop the webserver
+    ///
+    /// See [`shutdown`](crate::shutdown) for details.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown_handle.clone()
+    }
+
     /// Quick and dirty solution to expose the registered handlers after startup
     #[doc(hidden)]
     pub fn get_routes(&self) -> &[RluneRoute] {
@@ -64,15 +123,11 @@ pub struct ModuleBuilder {
 }
 
 impl ModuleBuilder {
-    fn new() -> ModuleBuilder {
+    fn new(logging: &LoggingSetup) -> ModuleBuilder {
         #[cfg(feature = "panic-hook")]
         crate::panic_hook::set_panic_hook();
 
-        let registry = tracing_subscriber::registry()
-            .with(EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new(Level::INFO.as_str())))
-            .with(tracing_subscriber::fmt::layer());
-
-        if registry.try_init().is_ok() {
+        if logging::init(logging) {
             debug!("Initialized rlune's subscriber");
         } else {
             debug!("Using external subscriber");
@@ -87,16 +142,68 @@ impl ModuleBuilder {
         self
     }
 
+    /// Register a module as provider of the interface `I`
+    ///
+    /// See [`RegistryBuilder::register_interface`].
+    pub fn register_interface<I: ?Sized + 'static, M: ProvidesInterface<I>>(
+        &mut self,
+    ) -> &mut Self {
+        self.modules.register_interface::<I, M>();
+        self
+    }
+
+    /// Sets the time each module is granted to shut down
+    ///
+    /// Defaults to 10 seconds.
+    pub fn shutdown_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.modules.shutdown_timeout(timeout);
+        self
+    }
+
     pub async fn init_modules(&mut self) -> Result<RouterBuilder, RluneError> {
         self.modules.init().await?;
         Ok(RouterBuilder {
             routes: RluneRouter::new(),
+            session: SessionSetup::default(),
+            listeners: Vec::new(),
+            layers: Vec::new(),
+            request_tracing: true,
+            metrics: true,
+            #[cfg(feature = "cors")]
+            cors: None,
+            shutdown: ShutdownSetup::default(),
+            shutdown_handle: ShutdownHandle::new(),
+            #[cfg(feature = "tls")]
+            tls: None,
         })
     }
 }
 
 pub struct RouterBuilder {
     routes: RluneRouter,
+    session: SessionSetup,
+
+    /// The addresses to listen on and the router to serve if it isn't `routes`
+    listeners: Vec<(ListenAddr, Option<RluneRouter>)>,
+
+    /// Layers applied to every router before it is finished
+    layers: Vec<Box<dyn Fn(RluneRouter) -> RluneRouter + Send + Sync>>,
+
+    /// Whether the request tracing middleware is applied
+    request_tracing: bool,
+
+    /// Whether the requests are recorded in the metrics
+    metrics: bool,
+
+    /// The CORS metadata of routes without their own
+    #[cfg(feature = "cors")]
+    cors: Option<Arc<CorsMetadata>>,
+
+    shutdown: ShutdownSetup,
+    shutdown_handle: ShutdownHandle,
+
+    #[cfg(feature = "tls")]
+    tls: Option<TlsSetup>,
 }
 
 impl RouterBuilder {
@@ -107,29 +214,375 @@ impl RouterBuilder {
         self
     }
 
-    /// Starts the webserver
+    /// Applies a [`tower::Layer`] to every route
+    ///
+    /// Unlike [`RluneRouter::layer`], the layer also applies to routes added afterward
+    /// and to routers added using [`listen_router`](Self::listen_router).
+    /// Layers added later wrap the ones added earlier.
+    ///
+    /// The layer can read the route's metadata from the [`MatchedRoute`](rlune_core::router::MatchedRoute)
+    /// in the request's extensions.
+    pub fn layer<L>(&mut self, layer: L) -> &mut Self
+    where
+        L: Layer<Route> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request> + Clone + Send + Sync + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.layers.push(Box::new(move |router: RluneRouter| {
+            router.layer(layer.clone())
+        }));
+        self
+    }
+
+    /// Enables or disables the request tracing
+    ///
+    /// When enabled (the default) every request
+    /// - is assigned a [`RequestId`](rlune_core::stuff::request_id::RequestId)
+    ///   which is returned in the `X-Request-Id` header and error responses
+    /// - is handled inside a span carrying its method, route, handler and request id
+    /// - is logged with its status and latency once handled
+    ///
+    /// The middleware wraps the layers added using [`layer`](Self::layer).
+    pub fn request_tracing(&mut self, enabled: bool) -> &mut Self {
+        self.request_tracing = enabled;
+        self
+    }
+
+    /// Enables or disables recording requests in the [`metrics`](crate::metrics)
+    ///
+    /// It is enabled by default.
+    /// Serve [`metrics::router`](crate::metrics::router) to expose the metrics.
+    pub fn metrics(&mut self, enabled: bool) -> &mut Self {
+        self.metrics = enabled;
+        self
+    }
+
+    /// Configures which origins may access routes without their own [`CorsMetadata`]
+    ///
+    /// See [`cors`](crate::cors) for details.
+    #[cfg(feature = "cors")]
+    pub fn cors(&mut self, metadata: CorsMetadata) -> &mut Self {
+        self.cors = Some(Arc::new(metadata));
+        self
+    }
+
+    /// Configures how sessions are handled
+    ///
+    /// Defaults to [`SessionSetup::default`].
+    pub fn session(&mut self, setup: SessionSetup) -> &mut Self {
+        self.session = setup;
+        self
+    }
+
+    /// Configures how the webserver shuts down
+    ///
+    /// Defaults to [`ShutdownSetup::default`].
+    pub fn shutdown(&mut self, setup: ShutdownSetup) -> &mut Self {
+        self.shutdown = setup;
+        self
+    }
+
+    /// Gets a handle to stop the webserver once it has been started
+    ///
+    /// See [`shutdown`](crate::shutdown) for details.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown_handle.clone()
+    }
+
+    /// Serves https instead of plain http
+    ///
+    /// See [`tls`](crate::tls) for details.
+    #[cfg(feature = "tls")]
+    pub fn tls(&mut self, setup: TlsSetup) -> &mut Self {
+        self.tls = Some(setup);
+        self
+    }
+
+    /// Adds an address to serve the routes on
+    ///
+    /// See [`listener`](crate::listener) for details.
+    pub fn listen(&mut self, addr: impl Into<ListenAddr>) -> &mut Self {
+        self.listeners.push((addr.into(), None));
+        self
+    }
+
+    /// Adds an address to serve a separate router on
+    ///
+    /// This can be used to expose internal handlers on an address which isn't public.
+    /// The router uses the same sessions as the main routes but never tls
+    /// and its handlers are not included in [`Rlune::get_routes`].
+    pub fn listen_router(&mut self, addr: impl Into<ListenAddr>, router: RluneRouter) -> &mut Self {
+        self.listeners.push((addr.into(), Some(router)));
+        self
+    }
+
+    /// Starts the webserver on a single tcp address
+    ///
+    /// This is a shorthand for [`listen`](Self::listen) followed by [`serve`](Self::serve).
     pub async fn start(&mut self, socket_addr: SocketAddr) -> Result<(), RluneError> {
-        let (router, routes) = mem::take(&mut self.routes).finish();
+        self.listen(socket_addr);
+        self.serve().await
+    }
+
+    /// Starts the webserver on every address added using [`listen`](Self::listen)
+    /// or [`listen_router`](Self::listen_router)
+    ///
+    /// All listeners share a single graceful shutdown.
+    /// Returns once every listener has stopped and the modules have been shut down.
+    pub async fn serve(&mut self) -> Result<(), RluneError> {
+        if self.listeners.is_empty() {
+            return Err(RluneError::NoListener);
+        }
 
-        INSTANCE.set(Rlune { routes })
+        let routes = mem::take(&mut self.routes);
+        let (mut router, routes) = self.apply_layers(routes).finish();
+        let sessions = session::build(&self.session)?;
+        if let Some(sessions) = &sessions {
+            router = router.layer(sessions.layer.clone());
+            if let (Some(store), Some(period)) =
+                (sessions.store.clone(), self.session.cleanup_interval)
+            {
+                session::spawn_cleanup(store, period);
+            }
+        }
+        session::register_metrics(&self.session)?;
+
+        let shutdown_handle = self.shutdown_handle.clone();
+        INSTANCE.set(Rlune { routes, shutdown_handle: shutdown_handle.clone() })
             .unwrap_or_else(|_| panic!("Rlune has already been started. There can't be more than one instance per process."));
 
-        let socket = TcpListener::bind(socket_addr).await?;
+        #[cfg(feature = "reload-signal")]
+        let reload_signal = tokio::spawn(crate::reload_signal::reload_on_signal()?);
+
+        #[cfg(feature = "graceful-shutdown")]
+        let shutdown_signal = {
+            debug!("Registering signals for graceful shutdown");
+            tokio::spawn(crate::graceful_shutdown::forward_signals(
+                shutdown_handle.clone(),
+            )?)
+        };
+        let shutdown_coordinator = tokio::spawn(crate::shutdown::coordinate(
+            shutdown_handle.clone(),
+            self.shutdown.clone(),
+        ));
+
+        #[cfg(feature = "tls")]
+        let tls = match self.tls.take() {
+            Some(setup) => {
+                if let Some(max_age) = setup.hsts {
+                    router = crate::tls::add_hsts(router, max_age);
+                }
+                Some((crate::tls::acceptor(&setup)?, setup))
+            }
+            None => None,
+        };
+        #[cfg(feature = "tls")]
+        let mut https_port = None;
+
+        let mut servers = Vec::new();
+        for (addr, separate_router) in mem::take(&mut self.listeners) {
+            #[cfg(feature = "tls")]
+            let is_main = separate_router.is_none();
+            let router = match separate_router {
+                None => router.clone(),
+                Some(separate_router) => {
+                    let (mut separate_router, _) = self.apply_layers(separate_router).finish();
+                    if let Some(sessions) = &sessions {
+                        separate_router = separate_router.layer(sessions.layer.clone());
+                    }
+                    separate_router
+                }
+            };
 
-        info!("Starting to serve webserver on http://{socket_addr}");
-        let serve_future = axum::serve(socket, router.layer(session::layer()));
+            let listener = listener::bind(&addr).await?;
 
-        debug!("Registering signals for graceful shutdown");
+            #[cfg(feature = "tls")]
+            let listener = match (listener, &tls) {
+                (BoundListener::Tcp(listener), Some((acceptor, setup))) if is_main => {
+                    let listener =
+                        TlsListener::new(listener, acceptor.clone(), setup.handshake_timeout)?;
+                    let local_addr = listener.local_addr()?;
+                    https_port.get_or_insert(local_addr.port());
+                    info!("Starting to serve webserver on https://{local_addr}");
+                    servers.push(tokio::spawn(serve(
+                        listener,
+                        router,
+                        shutdown_handle.clone(),
+                    )));
+                    continue;
+                }
+                (listener, _) => listener,
+            };
+
+            servers.push(match listener {
+                BoundListener::Tcp(listener) => {
+                    info!(
+                        "Starting to serve webserver on http://{}",
+                        listener.local_addr()?
+                    );
+                    tokio::spawn(serve(listener, router, shutdown_handle.clone()))
+                }
+                #[cfg(unix)]
+                BoundListener::Unix(listener, path) => {
+                    info!("Starting to serve webserver on {addr}");
+                    let shutdown_handle = shutdown_handle.clone();
+                    tokio::spawn(async move {
+                        let result = serve(listener, router, shutdown_handle).await;
+                        if let Some(path) = path {
+                            if let Err(error) = fs::remove_file(&path) {
+                                debug!(error.display = %error, "Failed to remove unix socket");
+                            }
+                        }
+                        result
+                    })
+                }
+            });
+        }
+
+        #[cfg(feature = "tls")]
+        if let (Some((_, setup)), Some(https_port)) = (&tls, https_port) {
+            if let Some(redirect_addr) = setup.redirect_http {
+                crate::tls::spawn_redirect(redirect_addr, https_port).await?;
+            }
+        }
+
+        let mut result = Ok(());
+        for server in servers {
+            let server_result = server
+                .await
+                .unwrap_or_else(|error| Err(io::Error::other(error)));
+            result = result.and(server_result);
+        }
+
+        shutdown_coordinator.abort();
         #[cfg(feature = "graceful-shutdown")]
-        let serve_future =
-            serve_future.with_graceful_shutdown(crate::graceful_shutdown::wait_for_signal()?);
+        shutdown_signal.abort();
+        #[cfg(feature = "reload-signal")]
+        reload_signal.abort();
 
-        serve_future.await?;
+        info!("Shutting down modules");
+        let shutdown_result = Registry::global().shutdown().await;
 
+        #[cfg(feature = "opentelemetry")]
+        crate::otel::shutdown().await;
+
+        result?;
+        shutdown_result?;
         Ok(())
     }
+
+    /// Applies the layers added using [`layer`](Self::layer) and rlune's middleware to a router
+    fn apply_layers(&self, router: RluneRouter) -> RluneRouter {
+        let mut router = self
+            .layers
+            .iter()
+            .fold(router, |router, layer| layer(router));
+        // Wraps the layers, so they don't reject preflight requests
+        #[cfg(feature = "cors")]
+        {
+            router = router.layer(middleware::from_fn_with_state(
+                self.cors.clone(),
+                crate::cors::middleware::cors_middleware,
+            ));
+        }
+        if self.metrics {
+            router = router.layer(middleware::from_fn(metrics::record_request));
+        }
+        if self.request_tracing {
+            router = router.layer(middleware::from_fn(request_tracing::trace_request));
+        }
+        // Provides the csrf middleware with the session's cookie attributes
+        #[cfg(feature = "csrf")]
+        {
+            router = router.layer(Extension(crate::csrf::middleware::CsrfCookie::new(
+                &self.session,
+            )));
+        }
+        router
+    }
 }
 
-static INSTANCE: OnceLock<Rlune> = OnceLock::new();
+/// Serves `router` on `listener` until its connections have been drained or closed
+///
+/// The listener stops accepting once the shutdown reaches [`ShutdownPhase::Draining`].
+/// Connections still open once it reaches [`ShutdownPhase::Forced`] are aborted.
+/// In both cases the future only resolves after every connection task has finished.
+async fn serve<L>(
+    mut listener: L,
+    router: Router,
+    shutdown_handle: ShutdownHandle,
+) -> io::Result<()>
+where
+    L: Listener,
+    L::Addr: Debug,
+{
+    let mut connections = JoinSet::new();
+
+    loop {
+        let accepted = future::or(async { Some(listener.accept().await) }, async {
+            shutdown_handle.reached(ShutdownPhase::Draining).await;
+            None
+        })
+        .await;
+        let Some((io, remote_addr)) = accepted else {
+            break;
+        };
+        trace!("Accepted connection from {remote_addr:?}");
+
+        // Forget about connections which have already been closed
+        while connections.try_join_next().is_some() {}
 
+        let service = TowerToHyperService::new(
+            router
+                .clone()
+                .map_request(|request: Request<Incoming>| request.map(Body::new)),
+        );
+        let connection = auto::Builder::new(TokioExecutor::new())
+            .serve_connection_with_upgrades(TokioIo::new(io), service)
+            .into_owned();
+        let draining = shutdown_handle.clone();
+        connections.spawn(async move {
+            let mut connection = pin!(connection);
+            let finished = future::or(async { Some(connection.as_mut().await) }, async {
+                draining.reached(ShutdownPhase::Draining).await;
+                None
+            })
+            .await;
+            let result = match finished {
+                Some(result) => result,
+                None => {
+                    // Finishes the current request before closing the connection
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(error) = result {
+                trace!(error.display = %error, "Failed to serve connection");
+            }
+        });
+    }
+    drop(listener);
+
+    let forced = future::or(
+        async {
+            while connections.join_next().await.is_some() {}
+            false
+        },
+        async {
+            shutdown_handle.reached(ShutdownPhase::Forced).await;
+            true
+        },
+    )
+    .await;
+    if forced {
+        debug!("Closing {} connections", connections.len());
+        connections.abort_all();
+    }
+    while connections.join_next().await.is_some() {}
 
+    Ok(())
+}
+
+static INSTANCE: OnceLock<Rlune> = OnceLock::new();
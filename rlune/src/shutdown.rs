@@ -0,0 +1,152 @@
+//! Stopping the webserver gracefully
+//!
+//! A shutdown is requested by a termination signal like `SIGTERM`
+//! (if the `graceful-shutdown` feature is enabled) or a [`ShutdownHandle`] and runs in phases:
+//!
+//! 1. The [readiness handler](crate::health::get_ready) starts to report "not ready"
+//!    while requests are still served for [`ShutdownSetup::readiness_delay`].
+//!    This gives load balancers time to stop sending new requests.
+//! 2. The listeners stop accepting connections and the open ones are drained.
+//! 3. Connections still open after [`ShutdownSetup::drain_timeout`] are closed.
+//!    A second signal or [`ShutdownHandle::force`] skips right to this phase.
+//!
+//! Afterward, the modules are shut down.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_lite::future;
+use rlune_core::registry::Registry;
+use tokio::sync::watch;
+use tracing::info;
+use tracing::warn;
+
+/// Declares how the webserver is shut down
+#[derive(Debug, Clone)]
+pub struct ShutdownSetup {
+    /// The time between reporting "not ready" and no longer accepting connections
+    ///
+    /// Defaults to no delay.
+    pub readiness_delay: Duration,
+
+    /// The time open connections are granted to finish their requests
+    ///
+    /// Defaults to 20 seconds which leaves time for the modules' shutdown
+    /// within kubernetes' default grace period of 30 seconds.
+    pub drain_timeout: Duration,
+}
+
+impl Default for ShutdownSetup {
+    fn default() -> Self {
+        Self {
+            readiness_delay: Duration::ZERO,
+            drain_timeout: Duration::from_secs(20),
+        }
+    }
+}
+
+/// The phases of a shutdown
+///
+/// The phase only ever advances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ShutdownPhase {
+    /// The webserver is serving requests
+    Running,
+
+    /// A shutdown has been requested and the readiness delay is running
+    Requested,
+
+    /// The listeners stopped accepting connections and the open ones are drained
+    Draining,
+
+    /// Open connections are closed
+    Forced,
+}
+
+/// Handle to stop the webserver programmatically
+///
+/// Get it from [`RouterBuilder::shutdown_handle`](crate::RouterBuilder::shutdown_handle)
+/// or [`Rlune::shutdown_handle`](crate::Rlune::shutdown_handle).
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle(Arc<watch::Sender<ShutdownPhase>>);
+
+impl ShutdownHandle {
+    /// Constructs a handle for a webserver which is not shutting down
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(watch::Sender::new(ShutdownPhase::Running)))
+    }
+
+    /// Requests a graceful shutdown
+    ///
+    /// This behaves like receiving the first termination signal.
+    pub fn shutdown(&self) {
+        self.advance(ShutdownPhase::Requested);
+    }
+
+    /// Closes all connections without waiting for them to drain
+    ///
+    /// This behaves like receiving the second termination signal.
+    pub fn force(&self) {
+        self.advance(ShutdownPhase::Forced);
+    }
+
+    /// Checks whether a shutdown has been requested
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow() >= ShutdownPhase::Requested
+    }
+
+    /// Moves the shutdown to `phase` unless it is already further along
+    pub(crate) fn advance(&self, phase: ShutdownPhase) {
+        self.0.send_if_modified(|current| {
+            if *current < phase {
+                *current = phase;
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    /// Resolves once the shutdown reached `phase`
+    pub(crate) async fn reached(&self, phase: ShutdownPhase) {
+        let mut receiver = self.0.subscribe();
+        // An error means the sender has been dropped which can't happen while `self` exists
+        let _ = receiver.wait_for(|current| *current >= phase).await;
+    }
+}
+
+/// Advances a requested shutdown through its phases according to `setup`
+///
+/// The future resolves once connections are supposed to be closed.
+pub(crate) async fn coordinate(handle: ShutdownHandle, setup: ShutdownSetup) {
+    handle.reached(ShutdownPhase::Requested).await;
+    info!("Shutdown requested, reporting not ready");
+    Registry::global().begin_shutdown();
+
+    future::or(
+        tokio::time::sleep(setup.readiness_delay),
+        handle.reached(ShutdownPhase::Forced),
+    )
+    .await;
+    info!("Draining connections");
+    handle.advance(ShutdownPhase::Draining);
+
+    let forced = future::or(
+        async {
+            tokio::time::sleep(setup.drain_timeout).await;
+            false
+        },
+        async {
+            handle.reached(ShutdownPhase::Forced).await;
+            true
+        },
+    )
+    .await;
+    if !forced {
+        warn!(
+            "Connections didn't drain within {:?}, closing them",
+            setup.drain_timeout
+        );
+    }
+    handle.advance(ShutdownPhase::Forced);
+}
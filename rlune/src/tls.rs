@@ -0,0 +1,427 @@
+//! Serving the webserver over https
+//!
+//! Configure it with [`RouterBuilder::tls`](crate::RouterBuilder::tls):
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use rlune::tls::TlsSetup;
+//! # async fn start(mut builder: rlune::RouterBuilder) -> Result<(), rlune::error::RluneError> {
+//! let mut tls = TlsSetup::new("/etc/tls/tls.crt", "/etc/tls/tls.key");
+//! tls.redirect_http = Some("[::]:80".parse().unwrap());
+//! tls.hsts = Some(Duration::from_secs(60 * 60 * 24 * 365));
+//! builder.tls(tls).start("[::]:443".parse().unwrap()).await
+//! # }
+//! ```
+//!
+//! Tls is used for every tcp listener serving the main routes
+//! (see [`RouterBuilder::listen`](crate::RouterBuilder::listen)).
+//!
+//! The certificate and key files are checked for changes periodically
+//! and reloaded without interrupting established connections.
+//! This plays nicely with tools renewing certificates in place, like cert-manager.
+
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use axum::extract::Request;
+use axum::http::header;
+use axum::http::uri::Authority;
+use axum::http::HeaderValue;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Redirect;
+use axum::response::Response;
+use axum::serve::Listener;
+use axum::Router;
+use rlune_core::registry::tasks::RestartPolicy;
+use rlune_core::registry::Registry;
+use rlune_core::stuff::swap_lock::SwapLock;
+use rustls::server::ClientHello;
+use rustls::server::ResolvesServerCert;
+use rustls::sign::CertifiedKey;
+use rustls::Certificate;
+use rustls::PrivateKey;
+use rustls::ServerConfig;
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+/// The number of established connections waiting to be served
+const ACCEPT_BACKLOG: usize = 64;
+
+/// The number of handshakes running or waiting for a free slot in the backlog
+///
+/// No more tcp connections are accepted until one of them finishes.
+const MAX_HANDSHAKES: usize = 256;
+
+/// Declares how https is served
+#[derive(Debug, Clone)]
+pub struct TlsSetup {
+    /// Path to the PEM encoded certificate chain
+    ///
+    /// The leaf certificate has to come first.
+    pub cert_path: PathBuf,
+
+    /// Path to the PEM encoded private key
+    ///
+    /// PKCS#8, PKCS#1 (RSA) and SEC1 (EC) keys are supported.
+    pub key_path: PathBuf,
+
+    /// How often the files are checked for changes
+    pub reload_interval: Duration,
+
+    /// The time a client is granted to complete the handshake
+    pub handshake_timeout: Duration,
+
+    /// Address to serve a plain http server on which redirects every request to https
+    pub redirect_http: Option<SocketAddr>,
+
+    /// The `max-age` of the `Strict-Transport-Security` header
+    ///
+    /// If set, the header is added to every response.
+    pub hsts: Option<Duration>,
+}
+
+impl TlsSetup {
+    /// Constructs a setup using the certificate and key at the given paths
+    ///
+    /// Neither the http redirect nor HSTS are enabled.
+    pub fn new(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            reload_interval: Duration::from_secs(60),
+            handshake_timeout: Duration::from_secs(10),
+            redirect_http: None,
+            hsts: None,
+        }
+    }
+}
+
+/// Error returned when the certificate or key can't be loaded
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("Failed to read '{}': {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("'{}' doesn't contain any certificate", .0.display())]
+    NoCertificate(PathBuf),
+
+    #[error("'{}' doesn't contain any private key", .0.display())]
+    NoPrivateKey(PathBuf),
+
+    #[error("The private key in '{}' is not supported", .0.display())]
+    UnsupportedKey(PathBuf),
+}
+
+/// Listener accepting tls connections
+///
+/// The handshakes are performed concurrently in the background,
+/// so a slow client can't block other connections from being accepted.
+pub(crate) struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    acceptor: JoinHandle<()>,
+}
+
+impl TlsListener {
+    /// Wraps a bound tcp listener to perform tls handshakes on its connections
+    pub(crate) fn new(
+        listener: TcpListener,
+        acceptor: TlsAcceptor,
+        handshake_timeout: Duration,
+    ) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (sender, connections) = mpsc::channel(ACCEPT_BACKLOG);
+        let acceptor = tokio::spawn(accept_connections(
+            listener,
+            acceptor,
+            handshake_timeout,
+            sender,
+        ));
+        Ok(Self {
+            local_addr,
+            connections,
+            acceptor,
+        })
+    }
+}
+
+/// Loads the certificate and spawns the task reloading it
+///
+/// The returned acceptor can be shared by several listeners.
+pub(crate) fn acceptor(setup: &TlsSetup) -> Result<TlsAcceptor, TlsError> {
+    let resolver = Arc::new(CertResolver::load(setup)?);
+    let reloading = resolver.clone();
+    Registry::global().tasks().spawn_periodic(
+        "tls-reload",
+        setup.reload_interval,
+        RestartPolicy::OnPanic,
+        move || {
+            let resolver = reloading.clone();
+            async move {
+                resolver.reload_if_changed();
+                Ok(())
+            }
+        },
+    );
+
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The acceptor only stops when the listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+impl Drop for TlsListener {
+    fn drop(&mut self) {
+        self.acceptor.abort();
+    }
+}
+
+/// Accepts tcp connections and spawns their handshakes
+async fn accept_connections(
+    mut listener: TcpListener,
+    acceptor: TlsAcceptor,
+    handshake_timeout: Duration,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    let handshakes = Arc::new(Semaphore::new(MAX_HANDSHAKES));
+    loop {
+        let Ok(permit) = handshakes.clone().acquire_owned().await else {
+            // The semaphore is never closed
+            return;
+        };
+        // axum's implementation logs and retries errors
+        let (stream, addr) = Listener::accept(&mut listener).await;
+        let acceptor = acceptor.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            // Held until the connection has been handed to the listener
+            let _permit = permit;
+            match tokio::time::timeout(handshake_timeout, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    // An error means the listener has been dropped
+                    let _ = sender.send((stream, addr)).await;
+                }
+                Ok(Err(error)) => {
+                    debug!(error.display = %error, client.addr = %addr, "TLS handshake failed");
+                }
+                Err(_) => debug!(client.addr = %addr, "TLS handshake timed out"),
+            }
+        });
+    }
+}
+
+/// Provides the current certificate to rustls and swaps it when the files change
+struct CertResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: SwapLock<Arc<CertifiedKey>>,
+
+    /// The files' modification times when `current` has been loaded
+    modified: Mutex<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl CertResolver {
+    /// Loads the certificate for the first time
+    fn load(setup: &TlsSetup) -> Result<Self, TlsError> {
+        let modified = (modified(&setup.cert_path), modified(&setup.key_path));
+        Ok(Self {
+            current: SwapLock::new(Arc::new(load_certified_key(
+                &setup.cert_path,
+                &setup.key_path,
+            )?)),
+            cert_path: setup.cert_path.clone(),
+            key_path: setup.key_path.clone(),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    /// Reloads the certificate if either file has been modified
+    ///
+    /// If the new files are invalid, the old certificate is kept
+    /// and the files will be tried again during the next check.
+    fn reload_if_changed(&self) {
+        let current = (modified(&self.cert_path), modified(&self.key_path));
+        let mut modified = self.modified.lock().unwrap_or_else(PoisonError::into_inner);
+        if *modified == current {
+            return;
+        }
+
+        match load_certified_key(&self.cert_path, &self.key_path) {
+            Ok(certified_key) => {
+                self.current.swap(Arc::new(certified_key));
+                *modified = current;
+                info!("Reloaded TLS certificate");
+            }
+            Err(error) => {
+                warn!(
+                    error.display = %error,
+                    error.debug = ?error,
+                    "Failed to reload TLS certificate, keeping the old one"
+                );
+            }
+        }
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.get())
+    }
+}
+
+/// Gets a file's modification time
+///
+/// Symlinks are followed, so swapping a symlink's target counts as modification.
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Reads a certificate chain and its private key from PEM files
+fn load_certified_key(cert_path: &Path, key_path: &Path) -> Result<CertifiedKey, TlsError> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|source| TlsError::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(cert_path)?).map_err(|source| TlsError::Io {
+        path: cert_path.to_path_buf(),
+        source,
+    })?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificate(cert_path.to_path_buf()));
+    }
+
+    let mut reader = open(key_path)?;
+    let key = loop {
+        let item = rustls_pemfile::read_one(&mut reader).map_err(|source| TlsError::Io {
+            path: key_path.to_path_buf(),
+            source,
+        })?;
+        match item {
+            Some(
+                rustls_pemfile::Item::PKCS8Key(key)
+                | rustls_pemfile::Item::RSAKey(key)
+                | rustls_pemfile::Item::ECKey(key),
+            ) => break key,
+            Some(_) => continue,
+            None => return Err(TlsError::NoPrivateKey(key_path.to_path_buf())),
+        }
+    };
+    let key = rustls::sign::any_supported_type(&PrivateKey(key))
+        .map_err(|_| TlsError::UnsupportedKey(key_path.to_path_buf()))?;
+
+    Ok(CertifiedKey::new(
+        certs.into_iter().map(Certificate).collect(),
+        key,
+    ))
+}
+
+/// Adds the `Strict-Transport-Security` header to every response
+pub(crate) fn add_hsts(router: Router, max_age: Duration) -> Router {
+    let value = HeaderValue::from_str(&format!("max-age={}", max_age.as_secs()))
+        .unwrap_or_else(|_| unreachable!("The header value only contains ascii characters"));
+    router.layer(axum::middleware::map_response(
+        move |mut response: Response| {
+            let value = value.clone();
+            async move {
+                response
+                    .headers_mut()
+                    .insert(header::STRICT_TRANSPORT_SECURITY, value);
+                response
+            }
+        },
+    ))
+}
+
+/// Binds the plain http server redirecting to https and spawns it as supervised task
+pub(crate) async fn spawn_redirect(socket_addr: SocketAddr, https_port: u16) -> io::Result<()> {
+    let mut listener = Some(TcpListener::bind(socket_addr).await?);
+    let router = Router::new()
+        .fallback(move |request: Request| async move { redirect_to_https(request, https_port) });
+
+    info!("Redirecting http://{socket_addr} to https");
+    Registry::global().tasks().spawn(
+        "https-redirect",
+        RestartPolicy::Never,
+        move |cancellation| {
+            let listener = listener.take();
+            let router = router.clone();
+            async move {
+                let listener = listener.ok_or("The redirect server can't be restarted")?;
+                axum::serve(listener, router)
+                    .with_graceful_shutdown(async move { cancellation.cancelled().await })
+                    .await?;
+                Ok(())
+            }
+        },
+    );
+    Ok(())
+}
+
+/// Responds with a permanent redirect to the same uri using https
+fn redirect_to_https(request: Request, https_port: u16) -> Response {
+    let authority = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok())
+        .or_else(|| request.uri().authority().cloned());
+    let Some(authority) = authority else {
+        return (StatusCode::BAD_REQUEST, "Missing host").into_response();
+    };
+
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    let location = match https_port {
+        443 => format!("https://{}{path}", authority.host()),
+        port => format!("https://{}:{port}{path}", authority.host()),
+    };
+    Redirect::permanent(&location).into_response()
+}